@@ -0,0 +1,779 @@
+use crate::cache::{IndexEntry, index_is_fresh, load_index, save_index};
+use crate::categories::{Category, in_category, matches_category};
+use crate::events::launch_app;
+use crate::history::{Associations, prefer_variant, preferred_variants, record_choice};
+use crate::matcher::{Term, app_keywords, app_score, fold, typo_score};
+use crate::menu::{MenuItem, MenuItems};
+use crate::provider::{Item, Provider};
+use crate::recent::files_for_app;
+use crate::utils::{ConfFile, get_conf_map};
+use dirs::home_dir;
+use gtk4::{
+    self as gtk, ApplicationWindow,
+    gio::{self, AppInfo, DesktopAppInfo, Icon, ThemedIcon},
+    glib,
+    prelude::*,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::process::Command;
+use std::rc::{Rc, Weak};
+
+// One-off permission changes offered for Flatpak apps when "flatpak-overrides" is missing.
+const DEFAULT_OVERRIDES: [(&str, &str); 2] = [
+    ("Run with host file access", "--filesystem=host"),
+    ("Run without network", "--unshare=network"),
+];
+
+/// Where an app was installed from, in the order picked when nothing was chosen yet.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Variant {
+    // A copy in ~/.local/share/applications.
+    Local,
+    System,
+    Flatpak,
+    Snap,
+}
+
+impl Variant {
+    pub fn of(appynka: &AppInfo) -> Self {
+        let Some(desktop_info) = appynka.downcast_ref::<DesktopAppInfo>() else {
+            return Variant::System;
+        };
+        let file = desktop_info.filename().unwrap_or_default();
+        if desktop_info.string("X-Flatpak").is_some() {
+            Variant::Flatpak
+        } else if desktop_info.string("X-SnapInstanceName").is_some()
+            || file.to_string_lossy().contains("/snapd/")
+        {
+            Variant::Snap
+        } else if file.starts_with(glib::user_data_dir().join("applications")) {
+            Variant::Local
+        } else {
+            Variant::System
+        }
+    }
+
+    /// Flatpak and Snap apps run in a sandbox, their results say so.
+    pub fn sandboxed(self) -> bool {
+        matches!(self, Variant::Flatpak | Variant::Snap)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Variant::Local => "Local",
+            Variant::System => "System",
+            Variant::Flatpak => "Flatpak",
+            Variant::Snap => "Snap",
+        }
+    }
+}
+
+/// The Flatpak app id or the Snap name of a sandboxed app.
+pub fn sandbox_id(appynka: &AppInfo) -> Option<String> {
+    let desktop_info = appynka.downcast_ref::<DesktopAppInfo>()?;
+    desktop_info
+        .string("X-Flatpak")
+        .or_else(|| desktop_info.string("X-SnapInstanceName"))
+        .map(|id| id.to_string())
+}
+
+/// What two installs of the same app have in common: the window class, the last part of
+/// the desktop id ("org.mozilla.firefox" and "firefox_firefox" both give "firefox") or
+/// the executable, each together with the name so unrelated apps stay apart.
+fn variant_keys(appynka: &AppInfo) -> Vec<String> {
+    let desktop_info = appynka.downcast_ref::<DesktopAppInfo>();
+    let name = fold(&appynka.name());
+    let first_word = name.split(' ').next().unwrap_or_default().to_string();
+    let mut keys = Vec::new();
+
+    if let Some(wm_class) = desktop_info.and_then(|d| d.startup_wm_class()) {
+        keys.push(format!("wm:{}|{}", wm_class.to_lowercase(), first_word));
+    }
+    if let Some(id) = appynka.id() {
+        let id = id.trim_end_matches(".desktop").to_lowercase();
+        let stem = id.split('_').next().unwrap_or_default();
+        let stem = stem.rsplit('.').next().unwrap_or_default();
+        keys.push(format!("id:{}|{}", stem, first_word));
+    }
+    let executable = appynka.executable();
+    let executable = executable
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    // These start the real app, they say nothing about it.
+    if !["", "flatpak", "snap", "env", "sh", "bash"].contains(&executable.as_str()) {
+        keys.push(format!("exec:{}|{}", executable, name));
+    }
+    keys
+}
+
+/// The shown apps with the installs of the same app grouped together.
+fn group_variants(app_infos: Vec<AppInfo>) -> Vec<Vec<AppInfo>> {
+    let mut groups: Vec<Vec<AppInfo>> = Vec::new();
+    let mut owners: HashMap<String, usize> = HashMap::new();
+    for appynka in app_infos.into_iter().filter(|a| a.should_show()) {
+        let keys = variant_keys(&appynka);
+        let index = match keys.iter().find_map(|key| owners.get(key).copied()) {
+            Some(index) => {
+                groups[index].push(appynka);
+                index
+            }
+            None => {
+                groups.push(vec![appynka]);
+                groups.len() - 1
+            }
+        };
+        for key in keys {
+            owners.entry(key).or_insert(index);
+        }
+    }
+    groups
+}
+
+// What `cat:` and `source:` terms score, the same as a keyword.
+const FILTER_SCORE: i64 = 100;
+
+/// The `app_score` fields only apps have, `cat:` and `source:`.
+fn app_field(appynka: &AppInfo, term: &Term) -> Option<i64> {
+    let hit = match term.field {
+        Some("cat") => matches_category(appynka, &term.text),
+        Some("source") => fold(Variant::of(appynka).label()) == term.text,
+        _ => false,
+    };
+    hit.then_some(FILTER_SCORE)
+}
+
+/// The install picked in the context menu before, or else the first by `Variant`.
+fn preferred<'a>(variants: &'a [AppInfo], preferred_ids: &[String]) -> &'a AppInfo {
+    variants
+        .iter()
+        .find(|a| {
+            a.id()
+                .is_some_and(|id| preferred_ids.contains(&id.to_string()))
+        })
+        .or_else(|| variants.iter().min_by_key(|a| Variant::of(a)))
+        .unwrap_or(&variants[0])
+}
+
+fn app_icon(appynka: &AppInfo) -> Icon {
+    appynka
+        .icon()
+        .unwrap_or_else(|| ThemedIcon::new("application-x-executable").upcast())
+}
+
+/// The description of the app, and where it came from when it's sandboxed.
+fn app_subtitle(appynka: &AppInfo) -> Option<String> {
+    let kind = Variant::of(appynka);
+    match (appynka.description(), sandbox_id(appynka)) {
+        (Some(description), Some(id)) => Some(format!("{}\n{} {}", description, kind.label(), id)),
+        (None, Some(id)) => Some(format!("{} {}", kind.label(), id)),
+        (description, None) => description.map(|d| d.to_string()),
+    }
+}
+
+/// Which install it is, noted when there are others or it's sandboxed.
+fn app_badge(appynka: &AppInfo, variants: &[AppInfo]) -> Option<String> {
+    let kind = Variant::of(appynka);
+    (variants.len() > 1 || kind.sandboxed()).then(|| kind.label().to_string())
+}
+
+/// The app index of the grouped apps, each as its preferred install.
+fn index_entries(groups: &[Vec<AppInfo>], preferred_ids: &[String]) -> Vec<IndexEntry> {
+    groups
+        .iter()
+        .map(|variants| {
+            let appynka = preferred(variants, preferred_ids);
+            IndexEntry {
+                id: appynka.id().map(|id| id.to_string()).unwrap_or_default(),
+                name: appynka.name().to_string(),
+                icon: app_icon(appynka).to_string().map(|icon| icon.to_string()),
+                subtitle: app_subtitle(appynka),
+                badge: app_badge(appynka, variants),
+            }
+        })
+        .collect()
+}
+
+/// Reads the desktop files on the thread pool and rewrites the app index when it no
+/// longer matches them. Resolves to whether it did.
+pub async fn refresh_index() -> bool {
+    let refreshed = gio::spawn_blocking(|| {
+        let entries = index_entries(&group_variants(AppInfo::all()), &preferred_variants());
+        if load_index().as_ref() == Some(&entries) {
+            return false;
+        }
+        save_index(&entries).unwrap_or_else(|err| {
+            eprintln!("Failed to save the app index: {}", err);
+        });
+        true
+    });
+    refreshed.await.unwrap_or(false)
+}
+
+/// The installed apps, shared by everything listing them. Read when first needed
+/// and again after `refresh`, called when apps are installed or removed.
+#[derive(Clone, Default)]
+pub struct InstalledApps {
+    apps: Rc<RefCell<Option<Rc<Vec<AppInfo>>>>>,
+}
+
+impl InstalledApps {
+    pub fn all(&self) -> Rc<Vec<AppInfo>> {
+        self.apps
+            .borrow_mut()
+            .get_or_insert_with(|| Rc::new(AppInfo::all()))
+            .clone()
+    }
+
+    pub fn refresh(&self) {
+        self.apps.borrow_mut().take();
+    }
+}
+
+/// Each app with all its installs, Flatpak, Snap and native.
+struct Grouped {
+    // The read of the installed apps the groups were made from.
+    from: Rc<Vec<AppInfo>>,
+    groups: Vec<Vec<AppInfo>>,
+}
+
+/// The installed desktop apps, kept up to date as apps are installed and removed.
+pub struct AppsProvider {
+    installed: InstalledApps,
+    grouped: RefCell<Option<Grouped>>,
+    // The learned choices, read on the first query and again after a choice.
+    associations: Rc<RefCell<Option<Associations>>>,
+    // The installs picked in the context menu, read on the first query and again
+    // after a pick.
+    preferred_ids: Rc<RefCell<Option<Vec<String>>>>,
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl AppsProvider {
+    pub fn new(
+        installed: &InstalledApps,
+        window: &ApplicationWindow,
+        app: &gtk::Application,
+    ) -> Self {
+        Self {
+            installed: installed.clone(),
+            grouped: RefCell::new(None),
+            associations: Rc::new(RefCell::new(None)),
+            preferred_ids: Rc::new(RefCell::new(None)),
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+
+    /// The app as a result of `query`, choosing it is remembered for the query. The
+    /// other installs of the app can be picked in the context menu instead.
+    fn item(&self, appynka: &AppInfo, variants: &[AppInfo], score: i64, query: &str) -> Item {
+        let activate = {
+            let appynka = appynka.clone();
+            let query = query.to_string();
+            let associations = self.associations.clone();
+            let window = self.window.clone();
+            let app = self.app.clone();
+            Rc::new(move || {
+                window.close();
+                if let Some(id) = appynka.id() {
+                    record_choice(&query, &id);
+                    associations.borrow_mut().take();
+                }
+                launch_app(&appynka, &[], None);
+                app.quit();
+            })
+        };
+        // The other installs, then a submenu of recent files of the app, each opened with it.
+        let actions = {
+            let appynka = appynka.clone();
+            let variants = variants.to_vec();
+            let preferred_ids = self.preferred_ids.clone();
+            let window = self.window.clone();
+            let app = self.app.clone();
+            Rc::new(move || {
+                let mut items: MenuItems =
+                    variant_actions(&appynka, &variants, &preferred_ids, &window, &app);
+                items.extend(sandbox_actions(&appynka, &window, &app));
+                let mut recent: MenuItems = Vec::new();
+                for info in files_for_app(&appynka) {
+                    let appynka = appynka.clone();
+                    let window = window.clone();
+                    let app = app.clone();
+                    recent.push(MenuItem::Action(
+                        info.display_name().to_string(),
+                        std::boxed::Box::new(move || {
+                            window.close();
+                            launch_app(&appynka, &[&info.uri()], None);
+                            app.quit();
+                        }),
+                    ));
+                }
+                if !recent.is_empty() {
+                    items.push(MenuItem::Submenu("Recent files".to_string(), recent));
+                }
+                items
+            })
+        };
+
+        let mut item = Item::new(&appynka.name(), app_icon(appynka), activate);
+        item.subtitle = app_subtitle(appynka);
+        item.score = score;
+        item.actions = Some(actions);
+        item.badge = app_badge(appynka, variants);
+        let appynka = appynka.clone();
+        item.app_info = Some(Rc::new(move || Some(appynka.clone())));
+        item
+    }
+
+    /// Groups the installed apps unless that was done since they last changed, and
+    /// writes the app index for the next start when it's out of date.
+    fn ensure_loaded(&self) {
+        let from = self.installed.all();
+        let current = self.grouped.borrow().as_ref().map(|g| g.from.clone());
+        if current.is_some_and(|current| Rc::ptr_eq(&current, &from)) {
+            return;
+        }
+        let groups = group_variants(from.as_ref().clone());
+        let entries = index_entries(&groups, &self.preferred_ids());
+        *self.grouped.borrow_mut() = Some(Grouped { from, groups });
+        // A different preferred install or locale changes the index but no directory.
+        if !index_is_fresh() || load_index().as_ref() != Some(&entries) {
+            save_index(&entries).unwrap_or_else(|err| {
+                eprintln!("Failed to save the app index: {}", err);
+            });
+        }
+    }
+
+    fn preferred_ids(&self) -> Vec<String> {
+        self.preferred_ids
+            .borrow_mut()
+            .get_or_insert_with(preferred_variants)
+            .clone()
+    }
+
+    /// The startup grid from `$XDG_CACHE_HOME/void-launcher/apps.json` without reading a
+    /// single desktop file, `None` when a desktop file was added or removed since it was
+    /// written. Their context menus read the desktop files when one opens.
+    pub fn startup_items(self: &Rc<Self>) -> Option<Vec<Item>> {
+        if !index_is_fresh() {
+            return None;
+        }
+        let items = load_index()?
+            .into_iter()
+            .map(|entry| {
+                let icon = entry
+                    .icon
+                    .and_then(|icon| Icon::for_string(&icon).ok())
+                    .unwrap_or_else(|| ThemedIcon::new("application-x-executable").upcast());
+                let activate = {
+                    let id = entry.id.clone();
+                    let window = self.window.clone();
+                    let app = self.app.clone();
+                    Rc::new(move || {
+                        window.close();
+                        match DesktopAppInfo::new(&id) {
+                            Some(appynka) => launch_app(&appynka.upcast(), &[], None),
+                            None => eprintln!("\"{}\" was removed.", id),
+                        }
+                        app.quit();
+                    })
+                };
+                let actions = {
+                    let id = entry.id.clone();
+                    let apps = Rc::downgrade(self);
+                    Rc::new(move || indexed_actions(&apps, &id))
+                };
+                let app_info = {
+                    let id = entry.id.clone();
+                    Rc::new(move || DesktopAppInfo::new(&id).map(|appynka| appynka.upcast()))
+                };
+                let mut item = Item::new(&entry.name, icon, activate);
+                item.subtitle = entry.subtitle;
+                item.badge = entry.badge;
+                item.score = 300;
+                item.actions = Some(actions);
+                item.app_info = Some(app_info);
+                item
+            })
+            .collect();
+        Some(items)
+    }
+
+    /// The apps of the category, if any, each as its preferred install with all its installs.
+    fn apps(&self, category: Option<&Category>) -> Vec<(AppInfo, Vec<AppInfo>)> {
+        self.ensure_loaded();
+        let preferred_ids = self.preferred_ids();
+        self.grouped
+            .borrow()
+            .iter()
+            .flat_map(|grouped| &grouped.groups)
+            .map(|variants| {
+                (
+                    preferred(variants, &preferred_ids).clone(),
+                    variants.clone(),
+                )
+            })
+            .filter(|(appynka, _)| category.is_none_or(|c| in_category(appynka, c)))
+            .collect()
+    }
+
+    /// The apps of the category, if any, matching `query`.
+    fn query_in(&self, query: &str, category: Option<&Category>) -> Vec<Item> {
+        let mut associations = self.associations.borrow_mut();
+        let associations = associations.get_or_insert_with(Associations::load);
+        self.apps(category)
+            .into_iter()
+            .filter_map(|(preferred, variants)| {
+                // Any install's name counts, their names don't always agree. Terms like
+                // `source:flatpak` match single installs, the preferred one is shown
+                // when it matched and the best match otherwise.
+                let matched: Vec<(&AppInfo, i64)> = variants
+                    .iter()
+                    .filter_map(|a| Some((a, app_score(query, a, |term| app_field(a, term))?)))
+                    .collect();
+                let (appynka, score) = matched
+                    .iter()
+                    .find(|(a, _)| a.id() == preferred.id())
+                    .or_else(|| {
+                        matched
+                            .iter()
+                            .min_by_key(|(_, score)| std::cmp::Reverse(*score))
+                    })
+                    .copied()?;
+                let score = matched
+                    .iter()
+                    .map(|(_, score)| *score)
+                    .max()
+                    .unwrap_or(score);
+                let learned = variants
+                    .iter()
+                    .filter_map(|a| a.id())
+                    .map(|id| associations.boost(query, &id))
+                    .max()
+                    .unwrap_or(0);
+                let mut item = self.item(appynka, &variants, score + learned, query);
+                item.highlight(query);
+                Some(item)
+            })
+            .collect()
+    }
+
+    fn suggest_in(&self, query: &str, category: Option<&Category>) -> Vec<Item> {
+        self.apps(category)
+            .into_iter()
+            .filter_map(|(appynka, variants)| {
+                let score = typo_score(query, &appynka.name(), &app_keywords(&appynka))?;
+                Some(self.item(&appynka, &variants, score, query))
+            })
+            .collect()
+    }
+}
+
+/// Copying the app id of a Flatpak or Snap app, and for Flatpak apps running it once with
+/// each of the "flatpak-overrides", a menu label mapped to `flatpak run` options.
+fn sandbox_actions(
+    appynka: &AppInfo,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) -> MenuItems {
+    let Some(id) = sandbox_id(appynka) else {
+        return Vec::new();
+    };
+    let mut items: MenuItems = Vec::new();
+    {
+        let id = id.clone();
+        let window = window.clone();
+        let app = app.clone();
+        items.push(MenuItem::Action(
+            format!("Copy app ID {}", id),
+            std::boxed::Box::new(move || {
+                window.close();
+                if let Err(err) = Command::new("wl-copy").arg("--").arg(&id).spawn() {
+                    eprintln!("Failed to copy \"{}\": {}", id, err);
+                }
+                app.quit();
+            }),
+        ));
+    }
+    if Variant::of(appynka) != Variant::Flatpak {
+        return items;
+    }
+
+    let overrides = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok())
+        .and_then(|conf| get_conf_map(conf.read(), "flatpak-overrides"))
+        .unwrap_or_else(|| {
+            DEFAULT_OVERRIDES
+                .iter()
+                .map(|(label, options)| (label.to_string(), options.to_string()))
+                .collect()
+        });
+    for (label, options) in overrides {
+        let options = match glib::shell_parse_argv(&options) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("Failed to parse \"{}\": {}", options, err);
+                continue;
+            }
+        };
+        let id = id.clone();
+        let window = window.clone();
+        let app = app.clone();
+        items.push(MenuItem::Action(
+            label,
+            std::boxed::Box::new(move || {
+                window.close();
+                Command::new("flatpak")
+                    .arg("run")
+                    .args(&options)
+                    .arg(&id)
+                    .spawn()
+                    .map(|_| ())
+                    .unwrap_or_else(|err| {
+                        eprintln!("Failed to run {}: {}", id, err);
+                    });
+                app.quit();
+            }),
+        ));
+    }
+    items
+}
+
+/// "Use the Flatpak version" and the like for the installs not shown, choosing one
+/// launches it and keeps it as the shown one.
+fn variant_actions(
+    shown: &AppInfo,
+    variants: &[AppInfo],
+    preferred_ids: &Rc<RefCell<Option<Vec<String>>>>,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) -> MenuItems {
+    let ids: Vec<String> = variants
+        .iter()
+        .filter_map(|a| a.id().map(|id| id.to_string()))
+        .collect();
+    let mut items: MenuItems = Vec::new();
+    for variant in variants.iter().filter(|a| a.id() != shown.id()) {
+        let Some(id) = variant.id().map(|id| id.to_string()) else {
+            continue;
+        };
+        let kind = Variant::of(variant);
+        let same_kind = variants.iter().filter(|a| Variant::of(a) == kind).count();
+        let label = if same_kind > 1 {
+            format!("Use {}", id.trim_end_matches(".desktop"))
+        } else {
+            format!("Use the {} version", kind.label())
+        };
+        let variant = variant.clone();
+        let ids = ids.clone();
+        let preferred_ids = preferred_ids.clone();
+        let window = window.clone();
+        let app = app.clone();
+        items.push(MenuItem::Action(
+            label,
+            std::boxed::Box::new(move || {
+                prefer_variant(&id, &ids);
+                preferred_ids.borrow_mut().take();
+                window.close();
+                launch_app(&variant, &[], None);
+                app.quit();
+            }),
+        ));
+    }
+    items
+}
+
+/// The context menu of the indexed app `id`, the same as once the desktop files are read.
+fn indexed_actions(apps: &Weak<AppsProvider>, id: &str) -> MenuItems {
+    let Some(apps) = apps.upgrade() else {
+        return Vec::new();
+    };
+    apps.apps(None)
+        .into_iter()
+        .find(|(appynka, _)| appynka.id().is_some_and(|a| a == id))
+        .and_then(|(appynka, variants)| apps.item(&appynka, &variants, 0, "").actions)
+        .map(|actions| actions())
+        .unwrap_or_default()
+}
+
+impl Provider for AppsProvider {
+    fn name(&self) -> &'static str {
+        "apps"
+    }
+
+    fn title(&self) -> &'static str {
+        "Applications"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        self.query_in(query, None)
+    }
+
+    fn suggest(&self, query: &str) -> Vec<Item> {
+        self.suggest_in(query, None)
+    }
+}
+
+/// The apps of one category for `cat:` queries, searched by the shared apps provider.
+pub struct CategoryApps {
+    apps: Rc<AppsProvider>,
+    category: &'static Category,
+}
+
+impl CategoryApps {
+    pub fn new(apps: Rc<AppsProvider>, category: &'static Category) -> Self {
+        Self { apps, category }
+    }
+}
+
+impl Provider for CategoryApps {
+    fn name(&self) -> &'static str {
+        "apps"
+    }
+
+    fn title(&self) -> &'static str {
+        "Applications"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        self.apps.query_in(query, Some(self.category))
+    }
+
+    fn suggest(&self, query: &str) -> Vec<Item> {
+        self.apps.suggest_in(query, Some(self.category))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    // GIO skips desktop files whose program is missing, so each one gets a stub, in a
+    // directory of its own as tests run in parallel. Outside the applications
+    // directories the desktop id isn't set everywhere, tests go by the other keys.
+    pub fn desktop_app(file_name: &str, entries: &[(&str, &str)]) -> AppInfo {
+        let dir = std::env::temp_dir()
+            .join(format!("void-launcher-tests-{}", std::process::id()))
+            .join(CREATED.fetch_add(1, Ordering::Relaxed).to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let keyfile = glib::KeyFile::new();
+        keyfile.set_string("Desktop Entry", "Type", "Application");
+        for (key, value) in entries {
+            if *key == "Exec" {
+                let program = dir.join(value.split(' ').next().unwrap());
+                std::fs::write(&program, "#!/bin/sh\n").unwrap();
+                std::fs::set_permissions(&program, PermissionsExt::from_mode(0o755)).unwrap();
+                let exec = format!("{}/{}", dir.to_string_lossy(), value);
+                keyfile.set_string("Desktop Entry", key, &exec);
+            } else {
+                keyfile.set_string("Desktop Entry", key, value);
+            }
+        }
+        let file = dir.join(file_name);
+        keyfile.save_to_file(&file).unwrap();
+        DesktopAppInfo::from_filename(&file).unwrap().upcast()
+    }
+
+    fn file_name(appynka: &AppInfo) -> String {
+        appynka
+            .downcast_ref::<DesktopAppInfo>()
+            .and_then(|desktop_info| desktop_info.filename())
+            .and_then(|file| Some(file.file_name()?.to_string_lossy().to_string()))
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn variant_keys_name_the_class_and_executable() {
+        let flatpak = desktop_app(
+            "org.mozilla.firefox.desktop",
+            &[
+                ("Name", "Firefox Web Browser"),
+                ("Exec", "flatpak run org.mozilla.firefox %u"),
+                ("StartupWMClass", "Firefox"),
+            ],
+        );
+        let keys = variant_keys(&flatpak);
+        assert!(keys.contains(&"wm:firefox|firefox".to_string()));
+        // A wrapper says nothing about the app.
+        assert!(!keys.iter().any(|key| key.starts_with("exec:")));
+
+        let native = desktop_app(
+            "firefox.desktop",
+            &[("Name", "Firefox Web Browser"), ("Exec", "firefox %u")],
+        );
+        let keys = variant_keys(&native);
+        assert!(keys.contains(&"exec:firefox|firefox web browser".to_string()));
+        assert!(!keys.iter().any(|key| key.starts_with("wm:")));
+    }
+
+    #[test]
+    fn group_variants_joins_installs_of_the_same_app() {
+        let apps = vec![
+            desktop_app(
+                "org.gnome.TextEditor.desktop",
+                &[("Name", "Text Editor"), ("Exec", "gnome-text-editor %U")],
+            ),
+            desktop_app(
+                "org.mozilla.firefox.desktop",
+                &[
+                    ("Name", "Firefox"),
+                    ("Exec", "flatpak run org.mozilla.firefox"),
+                    ("StartupWMClass", "firefox"),
+                ],
+            ),
+            desktop_app(
+                "firefox.desktop",
+                &[
+                    ("Name", "Firefox"),
+                    ("Exec", "firefox %u"),
+                    ("StartupWMClass", "firefox"),
+                ],
+            ),
+            desktop_app(
+                "gimp.desktop",
+                &[("Name", "GIMP"), ("Exec", "gimp-2.10 %U")],
+            ),
+            // The same window class, but a different app.
+            desktop_app(
+                "foxtracker.desktop",
+                &[
+                    ("Name", "Fox Tracker"),
+                    ("Exec", "foxtracker"),
+                    ("StartupWMClass", "firefox"),
+                ],
+            ),
+            desktop_app(
+                "org.gimp.GIMP.desktop",
+                &[("Name", "GIMP"), ("Exec", "gimp-2.10 %U")],
+            ),
+            desktop_app(
+                "hidden.desktop",
+                &[
+                    ("Name", "Hidden"),
+                    ("Exec", "hidden"),
+                    ("NoDisplay", "true"),
+                ],
+            ),
+        ];
+        let files: Vec<Vec<String>> = group_variants(apps)
+            .iter()
+            .map(|group| group.iter().map(file_name).collect())
+            .collect();
+        assert_eq!(
+            files,
+            [
+                vec!["org.gnome.TextEditor.desktop"],
+                vec!["org.mozilla.firefox.desktop", "firefox.desktop"],
+                vec!["gimp.desktop", "org.gimp.GIMP.desktop"],
+                vec!["foxtracker.desktop"],
+            ]
+        );
+    }
+}
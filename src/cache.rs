@@ -0,0 +1,105 @@
+use dirs::cache_dir;
+use eyre::{Result, eyre};
+use gtk4::glib;
+use serde_json::{Map, Value, json};
+use std::fs;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+/// What the startup grid shows of an app, enough to draw it without its desktop file.
+#[derive(PartialEq)]
+pub struct IndexEntry {
+    pub id: String,
+    pub name: String,
+    // Serialized with `IconExt::to_string`.
+    pub icon: Option<String>,
+    pub subtitle: Option<String>,
+    pub badge: Option<String>,
+}
+
+fn index_file() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("void-launcher/apps.json"))
+}
+
+/// The directories desktop files are read from.
+fn application_dirs() -> Vec<PathBuf> {
+    std::iter::once(glib::user_data_dir())
+        .chain(glib::system_data_dirs())
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// Modification times of the application directories in milliseconds, they change
+/// whenever a desktop file is added, removed or replaced.
+fn dir_stamps() -> Map<String, Value> {
+    application_dirs()
+        .into_iter()
+        .map(|dir| {
+            let stamp = fs::metadata(&dir)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_millis() as u64)
+                .unwrap_or(0);
+            (dir.to_string_lossy().to_string(), json!(stamp))
+        })
+        .collect()
+}
+
+fn read_index() -> Option<Value> {
+    let contents = fs::read_to_string(index_file()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// The apps of `$XDG_CACHE_HOME/void-launcher/apps.json`, `None` without a cache.
+pub fn load_index() -> Option<Vec<IndexEntry>> {
+    let data = read_index()?;
+    let text = |value: &Value, key: &str| value.get(key)?.as_str().map(|s| s.to_string());
+    let apps = data
+        .get("apps")?
+        .as_array()?
+        .iter()
+        .filter_map(|app| {
+            Some(IndexEntry {
+                id: text(app, "id")?,
+                name: text(app, "name")?,
+                icon: text(app, "icon"),
+                subtitle: text(app, "subtitle"),
+                badge: text(app, "badge"),
+            })
+        })
+        .collect();
+    Some(apps)
+}
+
+/// Whether no desktop file was added or removed since the index was written.
+pub fn index_is_fresh() -> bool {
+    read_index()
+        .and_then(|data| data.get("dirs").and_then(|d| d.as_object()).cloned())
+        .is_some_and(|dirs| dirs == dir_stamps())
+}
+
+pub fn save_index(entries: &[IndexEntry]) -> Result<()> {
+    let file = index_file().ok_or(eyre!("Couldn't locate the cache directory!"))?;
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let apps: Vec<Value> = entries
+        .iter()
+        .map(|entry| {
+            json!({
+                "id": entry.id,
+                "name": entry.name,
+                "icon": entry.icon,
+                "subtitle": entry.subtitle,
+                "badge": entry.badge,
+            })
+        })
+        .collect();
+    let data = json!({ "dirs": dir_stamps(), "apps": apps });
+
+    let tmp = file.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(&data)?)?;
+    fs::rename(tmp, file)?;
+    Ok(())
+}
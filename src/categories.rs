@@ -0,0 +1,308 @@
+use crate::apps::InstalledApps;
+use crate::provider::{Item, Provider};
+use crate::ui::get_object;
+use crate::utils::{ConfFile, get_conf_option};
+use dirs::home_dir;
+use eyre::{Ok, Result};
+use gtk4::{
+    Box, Builder, Button, Entry, ScrolledWindow,
+    gio::{AppInfo, DesktopAppInfo, ThemedIcon},
+    prelude::*,
+};
+use std::rc::Rc;
+
+/// A group of the bar, made of one or more freedesktop main categories.
+pub struct Category {
+    // What follows `cat:` in the search bar.
+    pub key: &'static str,
+    pub label: &'static str,
+    icon: &'static str,
+    main: &'static [&'static str],
+}
+
+pub const CATEGORIES: [Category; 11] = [
+    Category {
+        key: "development",
+        label: "Development",
+        icon: "applications-development",
+        main: &["Development"],
+    },
+    Category {
+        key: "education",
+        label: "Education",
+        icon: "accessories-dictionary",
+        main: &["Education"],
+    },
+    Category {
+        key: "games",
+        label: "Games",
+        icon: "applications-games",
+        main: &["Game"],
+    },
+    Category {
+        key: "graphics",
+        label: "Graphics",
+        icon: "applications-graphics",
+        main: &["Graphics"],
+    },
+    Category {
+        key: "internet",
+        label: "Internet",
+        icon: "applications-internet",
+        main: &["Network"],
+    },
+    Category {
+        key: "multimedia",
+        label: "Multimedia",
+        icon: "applications-multimedia",
+        main: &["AudioVideo", "Audio", "Video"],
+    },
+    Category {
+        key: "office",
+        label: "Office",
+        icon: "applications-office",
+        main: &["Office"],
+    },
+    Category {
+        key: "science",
+        label: "Science",
+        icon: "applications-science",
+        main: &["Science"],
+    },
+    Category {
+        key: "settings",
+        label: "Settings",
+        icon: "preferences-desktop",
+        main: &["Settings"],
+    },
+    Category {
+        key: "system",
+        label: "System",
+        icon: "applications-system",
+        main: &["System"],
+    },
+    Category {
+        key: "utilities",
+        label: "Utilities",
+        icon: "applications-utilities",
+        main: &["Utility"],
+    },
+];
+
+// Widget name of the bar's button showing every app.
+const ALL: &str = "all";
+
+/// Whether the app's `Categories` key lists one of the category's main categories.
+pub fn in_category(appynka: &AppInfo, category: &Category) -> bool {
+    appynka
+        .downcast_ref::<DesktopAppInfo>()
+        .and_then(|desktop_info| desktop_info.categories())
+        .is_some_and(|categories| categories.split(';').any(|c| category.main.contains(&c)))
+}
+
+/// Whether the app is in the category with that key or label, or lists `name` itself
+/// in its `Categories` key, for `cat:` terms inside a query.
+pub fn matches_category(appynka: &AppInfo, name: &str) -> bool {
+    if let Some(category) = find_category(name) {
+        return in_category(appynka, category);
+    }
+    appynka
+        .downcast_ref::<DesktopAppInfo>()
+        .and_then(|desktop_info| desktop_info.categories())
+        .is_some_and(|categories| {
+            categories
+                .split(';')
+                .any(|c| !c.is_empty() && c.eq_ignore_ascii_case(name))
+        })
+}
+
+/// Queries starting with `cat:`, split into the typed category and the rest of the query.
+pub fn category_query(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix("cat:")?;
+    Some(rest.split_once(' ').unwrap_or((rest, "")))
+}
+
+/// The category named by its key or label, in any case.
+pub fn find_category(name: &str) -> Option<&'static Category> {
+    CATEGORIES
+        .iter()
+        .find(|c| c.key.eq_ignore_ascii_case(name) || c.label.eq_ignore_ascii_case(name))
+}
+
+/// The categories whose key starts with `name`, the ones `cat:<name>` may still become.
+pub fn completing_categories(name: &str) -> impl Iterator<Item = &'static Category> {
+    let name = name.to_lowercase();
+    CATEGORIES.iter().filter(move |c| c.key.starts_with(&name))
+}
+
+/// The categories starting with what was typed after `cat:`, choosing one completes the query.
+pub struct CategoriesProvider {
+    entry: Entry,
+}
+
+impl CategoriesProvider {
+    pub fn new(entry: &Entry) -> Self {
+        Self {
+            entry: entry.clone(),
+        }
+    }
+}
+
+impl Provider for CategoriesProvider {
+    fn name(&self) -> &'static str {
+        "categories"
+    }
+
+    fn title(&self) -> &'static str {
+        "Categories"
+    }
+
+    fn query(&self, name: &str) -> Vec<Item> {
+        completing_categories(name)
+            .map(|category| {
+                let entry = self.entry.clone();
+                let mut item = Item::new(
+                    category.label,
+                    ThemedIcon::new(category.icon).upcast(),
+                    Rc::new(move || {
+                        set_category(&entry, Some(category));
+                        entry.grab_focus_without_selecting();
+                    }),
+                );
+                item.subtitle = Some(format!("cat:{}", category.key));
+                item
+            })
+            .collect()
+    }
+}
+
+/// Puts `cat:<key>` in front of the query, or takes it away with `None`.
+pub fn set_category(entry: &Entry, category: Option<&Category>) {
+    let text = entry.text().to_string();
+    let query = category_query(&text).map_or(text.as_str(), |(_, query)| query);
+    let text = match category {
+        Some(category) => format!("cat:{} {}", category.key, query),
+        None => query.to_string(),
+    };
+    entry.set_text(&text);
+    entry.set_position(-1);
+}
+
+/// Fills the category bar with the categories that have apps, unless
+/// "category-bar" is "false".
+pub fn build_category_bar(builder: &Builder, installed: &InstalledApps) -> Result<()> {
+    let bar: Box = get_object(builder, "category-bar")?;
+    let entry: Entry = get_object(builder, "search-entry")?;
+    let conf = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+    let enabled = conf
+        .map(|conf| get_conf_option(conf.read(), "category-bar"))
+        .unwrap_or_default();
+    if enabled == "false" {
+        return Ok(());
+    }
+    if enabled != "true" {
+        println!(
+            "\"{}\" isn't a valid value for \"category-bar\", going with default: \"true\".",
+            enabled
+        );
+    }
+
+    fill_category_bar(&bar, &entry, installed);
+    let scroller: ScrolledWindow = get_object(builder, "category-scroller")?;
+    scroller.set_visible(true);
+    highlight_category(builder, "");
+    Ok(())
+}
+
+/// Fills the bar again after apps were installed or removed, installing the first
+/// game or removing the last one changes the categories.
+pub fn refresh_category_bar(builder: &Builder, installed: &InstalledApps) {
+    let shown = builder
+        .object::<ScrolledWindow>("category-scroller")
+        .is_some_and(|scroller| scroller.is_visible());
+    let bar = builder.object::<Box>("category-bar");
+    let entry = builder.object::<Entry>("search-entry");
+    let (Some(bar), Some(entry)) = (bar.filter(|_| shown), entry) else {
+        return;
+    };
+    while let Some(child) = bar.first_child() {
+        bar.remove(&child);
+    }
+    fill_category_bar(&bar, &entry, installed);
+    highlight_category(builder, &entry.text());
+}
+
+fn fill_category_bar(bar: &Box, entry: &Entry, installed: &InstalledApps) {
+    let all = installed.all();
+    let app_infos: Vec<&AppInfo> = all.iter().filter(|appynka| appynka.should_show()).collect();
+    let all = std::iter::once(None);
+    let used = CATEGORIES
+        .iter()
+        .filter(|category| app_infos.iter().any(|a| in_category(a, category)))
+        .map(Some);
+    for category in all.chain(used) {
+        let button = Button::with_label(category.map_or("All", |c| c.label));
+        button.set_widget_name(category.map_or(ALL, |c| c.key));
+        button.set_focus_on_click(false);
+        button.set_can_focus(false);
+        button.add_css_class("flat");
+        button.add_css_class("category-button");
+        let entry = entry.clone();
+        button.connect_clicked(move |_| {
+            set_category(&entry, category);
+            entry.grab_focus_without_selecting();
+        });
+        bar.append(&button);
+    }
+}
+
+/// Marks the bar's button of the category the query is in.
+pub fn highlight_category(builder: &Builder, text: &str) {
+    let Some(bar) = builder.object::<Box>("category-bar") else {
+        return;
+    };
+    let current = category_query(text)
+        .and_then(|(name, _)| find_category(name))
+        .map_or(ALL, |category| category.key);
+    let mut child = bar.first_child();
+    while let Some(button) = child {
+        if button.widget_name() == current {
+            button.add_css_class("category-selected");
+        } else {
+            button.remove_css_class("category-selected");
+        }
+        child = button.next_sibling();
+    }
+}
+
+/// Moves to the next category of the bar, wrapping around through "All".
+pub fn cycle_category(builder: &Builder, backwards: bool) {
+    let (Some(bar), Some(entry)) = (
+        builder.object::<Box>("category-bar"),
+        builder.object::<Entry>("search-entry"),
+    ) else {
+        return;
+    };
+    let mut keys = Vec::new();
+    let mut child = bar.first_child();
+    while let Some(button) = child {
+        keys.push(button.widget_name().to_string());
+        child = button.next_sibling();
+    }
+    if keys.is_empty() {
+        return;
+    }
+
+    let text = entry.text().to_string();
+    let current = category_query(&text)
+        .and_then(|(name, _)| find_category(name))
+        .map_or(ALL, |category| category.key);
+    let index = keys.iter().position(|key| key == current).unwrap_or(0);
+    let next = if backwards {
+        (index + keys.len() - 1) % keys.len()
+    } else {
+        (index + 1) % keys.len()
+    };
+    set_category(&entry, find_category(&keys[next]));
+}
@@ -0,0 +1,576 @@
+use crate::matcher::matches;
+use crate::provider::{Item, Provider};
+use crate::utils::{ConfFile, get_conf_list, get_conf_option, string_to_u32};
+use dirs::{home_dir, state_dir};
+use eyre::{Result, eyre};
+use gtk4::{
+    self as gtk, ApplicationWindow,
+    gio::{self, FileIcon, Icon, ThemedIcon},
+    prelude::*,
+};
+use serde_json::{Value, json};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use wayland_client::{
+    Connection, Dispatch, Proxy, QueueHandle,
+    backend::ObjectId,
+    event_created_child,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::{wl_registry::WlRegistry, wl_seat::WlSeat},
+};
+use wayland_protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1::{self, ExtDataControlDeviceV1},
+    ext_data_control_manager_v1::ExtDataControlManagerV1,
+    ext_data_control_offer_v1::{self, ExtDataControlOfferV1},
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+};
+
+// Anything bigger is most likely a file manager copy of a huge image, not worth keeping.
+const MAX_ITEM_BYTES: usize = 10 * 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+const TEXT_MIMES: [&str; 4] = [
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+];
+const IMAGE_MIMES: [&str; 3] = ["image/png", "image/jpeg", "image/bmp"];
+// Password managers mark their secrets with these, see KeePassXC and KDE Klipper.
+const DEFAULT_IGNORE: [&str; 2] = [
+    "x-kde-passwordManagerHint",
+    "application/x-nspasteboard-concealed-type",
+];
+
+pub struct ClipEntry {
+    pub text: String,
+    pub image: Option<PathBuf>,
+    pub mime: String,
+    pub time: u64,
+}
+
+fn clipboard_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("void-launcher"))
+}
+
+fn history_file() -> Option<PathBuf> {
+    clipboard_dir().map(|dir| dir.join("clipboard.json"))
+}
+
+/// Reads the history written by `void-launcher --clipboard-daemon`, newest first.
+pub fn load_history() -> Vec<ClipEntry> {
+    let Some(contents) = history_file().and_then(|file| fs::read_to_string(file).ok()) else {
+        return Vec::new();
+    };
+    let data: Value = serde_json::from_str(&contents).unwrap_or(Value::Null);
+
+    data.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            Some(ClipEntry {
+                text: entry.get("text")?.as_str()?.to_string(),
+                image: entry
+                    .get("image")
+                    .and_then(|s| s.as_str())
+                    .map(PathBuf::from),
+                mime: entry.get("mime")?.as_str()?.to_string(),
+                time: entry.get("time").and_then(|t| t.as_u64()).unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+fn save_history(history: &[ClipEntry]) -> Result<()> {
+    let file = history_file().ok_or(eyre!("Couldn't locate the state directory!"))?;
+    let entries: Vec<Value> = history
+        .iter()
+        .map(|entry| {
+            json!({
+                "text": entry.text,
+                "image": entry.image.as_ref().map(|path| path.to_string_lossy()),
+                "mime": entry.mime,
+                "time": entry.time,
+            })
+        })
+        .collect();
+
+    // The history can hold anything the user copied, keep it private.
+    let tmp = file.with_extension("json.tmp");
+    let mut out = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)?;
+    out.write_all(serde_json::to_string(&entries)?.as_bytes())?;
+    fs::rename(tmp, file)?;
+    Ok(())
+}
+
+/// Splits the "clip " prefix off the query, with `--mode clipboard` no prefix is needed.
+pub fn clipboard_query<'a>(text: &'a str, mode: &str) -> Option<&'a str> {
+    if let Some(rest) = text.strip_prefix("clip ") {
+        Some(rest)
+    } else if mode == "clipboard" {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+/// The history of `void-launcher --clipboard-daemon`, newest first.
+pub struct ClipboardProvider {
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl ClipboardProvider {
+    pub fn new(window: &ApplicationWindow, app: &gtk::Application) -> Self {
+        Self {
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+}
+
+impl Provider for ClipboardProvider {
+    fn name(&self) -> &'static str {
+        "clipboard"
+    }
+
+    fn title(&self) -> &'static str {
+        "Clipboard"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let query = query.trim();
+        let mut items = Vec::new();
+        for entry in load_history() {
+            if entry.image.is_none() && !matches(query, &entry.text, &[]) {
+                continue;
+            }
+            if entry.image.is_some() && !query.is_empty() && !matches(query, &entry.mime, &[]) {
+                continue;
+            }
+
+            let icon: Icon = match &entry.image {
+                Some(path) => FileIcon::new(&gio::File::for_path(path)).upcast(),
+                None => ThemedIcon::new("edit-paste").upcast(),
+            };
+            let first_line = entry.text.lines().find(|l| !l.trim().is_empty());
+            let title = first_line.unwrap_or(&entry.text).trim().to_string();
+            let subtitle = entry.image.is_none().then(|| entry.text.clone());
+            let large_icon = entry.image.is_some();
+
+            let window = self.window.clone();
+            let app = self.app.clone();
+            let mut item = Item::new(
+                &title,
+                icon,
+                Rc::new(move || {
+                    window.close();
+                    if let Err(err) = copy_entry(&entry) {
+                        eprintln!("Failed to copy the clipboard entry: {}", err);
+                    }
+                    app.quit();
+                }),
+            );
+            item.subtitle = subtitle;
+            item.large_icon = large_icon;
+            items.push(item);
+        }
+        items
+    }
+}
+
+/// Hands the entry to wl-copy, which keeps serving it after the launcher exits.
+fn copy_entry(entry: &ClipEntry) -> Result<()> {
+    match &entry.image {
+        Some(path) => {
+            Command::new("wl-copy")
+                .args(["--type", &entry.mime])
+                .stdin(Stdio::from(File::open(path)?))
+                .spawn()?;
+        }
+        None => {
+            Command::new("wl-copy").arg("--").arg(&entry.text).spawn()?;
+        }
+    }
+    Ok(())
+}
+
+/// A data offer of either protocol, destroyed once it's of no use.
+enum Offer {
+    Ext(ExtDataControlOfferV1),
+    Wlr(ZwlrDataControlOfferV1),
+}
+
+impl Offer {
+    fn destroy(&self) {
+        match self {
+            Offer::Ext(offer) => offer.destroy(),
+            Offer::Wlr(offer) => offer.destroy(),
+        }
+    }
+}
+
+struct Daemon {
+    conn: Connection,
+    history: Vec<ClipEntry>,
+    // Offers announced but not yet made a selection, in the order they came, with
+    // their mime types.
+    offers: Vec<(ObjectId, Offer, Vec<String>)>,
+    max_entries: usize,
+    images: bool,
+    ignore: Vec<String>,
+}
+
+/// Entry point of `void-launcher --clipboard-daemon`, records every new selection.
+pub fn run_daemon() -> Result<()> {
+    let home = home_dir().ok_or(eyre!("Couldn't locate home directory!"))?;
+    let conf = ConfFile::new(home.join(".config/void-launcher/config.json"))?;
+    let mut max_entries = string_to_u32(get_conf_option(conf.read(), "clipboard-max-entries"));
+    if max_entries == 0 {
+        println!("\"clipboard-max-entries\" has to be a positive number, going with default: 100.");
+        max_entries = 100;
+    }
+    let images = get_conf_option(conf.read(), "clipboard-images") != "false";
+    let ignore = get_conf_list(conf.read(), "clipboard-ignore")
+        .unwrap_or_else(|| DEFAULT_IGNORE.iter().map(|s| s.to_string()).collect());
+
+    let dir = clipboard_dir().ok_or(eyre!("Couldn't locate the state directory!"))?;
+    // Only the user may look at the copied images.
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir.join("clipboard"))?;
+
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<Daemon>(&conn)?;
+    let qh = queue.handle();
+    let seat: WlSeat = globals.bind(&qh, 1..=1, ())?;
+
+    if let Ok(manager) = globals.bind::<ExtDataControlManagerV1, _, _>(&qh, 1..=1, ()) {
+        manager.get_data_device(&seat, &qh, ());
+    } else if let Ok(manager) = globals.bind::<ZwlrDataControlManagerV1, _, _>(&qh, 1..=2, ()) {
+        manager.get_data_device(&seat, &qh, ());
+    } else {
+        return Err(eyre!(
+            "The compositor supports neither ext-data-control nor wlr-data-control."
+        ));
+    }
+
+    let mut daemon = Daemon {
+        conn,
+        history: load_history(),
+        offers: Vec::new(),
+        max_entries: max_entries as usize,
+        images,
+        ignore,
+    };
+    loop {
+        queue.blocking_dispatch(&mut daemon)?;
+    }
+}
+
+/// The mime type to store an offer as, images first when they're kept. Offers with
+/// one of the `ignore` types aren't stored at all.
+fn pick_mime(mimes: &[String], images: bool, ignore: &[String]) -> Option<String> {
+    if mimes.iter().any(|mime| ignore.contains(mime)) {
+        return None;
+    }
+    let images = if images { &IMAGE_MIMES[..] } else { &[] };
+    images
+        .iter()
+        .chain(TEXT_MIMES.iter())
+        .find(|wanted| mimes.iter().any(|mime| mime == *wanted))
+        .map(|mime| mime.to_string())
+}
+
+impl Daemon {
+    /// Reads the offer through a socket the source client writes into from its own loop.
+    /// A socket rather than a pipe, so reading gives up on an owner that never finishes.
+    fn receive(&self, mime: &str, request: impl FnOnce(BorrowedFd)) -> Result<Vec<u8>> {
+        let (mut reader, writer) = UnixStream::pair()?;
+        request(writer.as_fd());
+        drop(writer);
+        self.conn.flush()?;
+
+        let deadline = Instant::now() + READ_TIMEOUT;
+        let mut data = Vec::new();
+        let mut chunk = [0u8; 64 * 1024];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(eyre!(
+                    "The clipboard owner didn't send \"{}\" in time.",
+                    mime
+                ));
+            }
+            reader.set_read_timeout(Some(left))?;
+            match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => data.extend_from_slice(&chunk[..read]),
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+            if data.len() > MAX_ITEM_BYTES {
+                return Err(eyre!("The clipboard entry is too big, skipping it."));
+            }
+        }
+        Ok(data)
+    }
+
+    fn record(&mut self, mime: String, data: Vec<u8>) -> Result<()> {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let entry = if IMAGE_MIMES.contains(&mime.as_str()) {
+            let mut hasher = DefaultHasher::new();
+            data.hash(&mut hasher);
+            let extension = mime.trim_start_matches("image/");
+            let dir = clipboard_dir().ok_or(eyre!("Couldn't locate the state directory!"))?;
+            let path =
+                dir.join("clipboard")
+                    .join(format!("{:016x}.{}", hasher.finish(), extension));
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&path)?
+                .write_all(&data)?;
+            ClipEntry {
+                text: format!("Image ({})", extension),
+                image: Some(path),
+                mime,
+                time,
+            }
+        } else {
+            let text = String::from_utf8_lossy(&data).to_string();
+            if text.trim().is_empty() {
+                return Ok(());
+            }
+            ClipEntry {
+                text,
+                image: None,
+                mime,
+                time,
+            }
+        };
+
+        self.history
+            .retain(|old| old.text != entry.text || old.image != entry.image);
+        self.history.insert(0, entry);
+        let kept = self.history.len().min(self.max_entries);
+        for dropped in self.history.split_off(kept) {
+            let unused = dropped
+                .image
+                .filter(|image| !self.history.iter().any(|e| e.image.as_ref() == Some(image)));
+            if let Some(image) = unused {
+                let _ = fs::remove_file(image);
+            }
+        }
+        save_history(&self.history)
+    }
+
+    /// Takes the mime types of the offer made a selection, the clipboard or the primary
+    /// one. The offers announced before it were replaced unused and are destroyed.
+    fn take_offer(&mut self, id: &ObjectId) -> Vec<String> {
+        let Some(position) = self.offers.iter().position(|(offer_id, ..)| offer_id == id) else {
+            return Vec::new();
+        };
+        let (_, _, mimes) = self.offers.remove(position);
+        for (_, offer, _) in self.offers.drain(..position) {
+            offer.destroy();
+        }
+        mimes
+    }
+
+    fn selection(&mut self, id: ObjectId, request: impl FnOnce(&str, BorrowedFd)) {
+        let mimes = self.take_offer(&id);
+        if let Some(mime) = pick_mime(&mimes, self.images, &self.ignore) {
+            let result = self
+                .receive(&mime, |fd| request(&mime, fd))
+                .and_then(|data| self.record(mime.clone(), data));
+            if let Err(err) = result {
+                eprintln!("Failed to store the clipboard entry: {}", err);
+            }
+        }
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for Daemon {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for Daemon {
+    fn event(
+        _: &mut Self,
+        _: &WlSeat,
+        _: <WlSeat as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+// ext-data-control and wlr-data-control are the same protocol under two names,
+// so both get identical handlers.
+macro_rules! data_control_dispatch {
+    ($manager:ty, $device:ty, $device_mod:ident, $offer:ty, $offer_mod:ident, $variant:path) => {
+        impl Dispatch<$manager, ()> for Daemon {
+            fn event(
+                _: &mut Self,
+                _: &$manager,
+                _: <$manager as Proxy>::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+            }
+        }
+
+        impl Dispatch<$device, ()> for Daemon {
+            fn event(
+                daemon: &mut Self,
+                _: &$device,
+                event: <$device as Proxy>::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                match event {
+                    $device_mod::Event::DataOffer { id } => {
+                        daemon.offers.push((id.id(), $variant(id), Vec::new()));
+                    }
+                    $device_mod::Event::Selection { id: Some(offer) } => {
+                        daemon.selection(offer.id(), |mime, fd| {
+                            offer.receive(mime.to_string(), fd)
+                        });
+                        offer.destroy();
+                    }
+                    $device_mod::Event::PrimarySelection { id: Some(offer) } => {
+                        daemon.take_offer(&offer.id());
+                        offer.destroy();
+                    }
+                    $device_mod::Event::Finished => {
+                        eprintln!("The compositor closed the clipboard device.");
+                        std::process::exit(1);
+                    }
+                    _ => {}
+                }
+            }
+
+            event_created_child!(Daemon, $device, [
+                $device_mod::EVT_DATA_OFFER_OPCODE => ($offer, ()),
+            ]);
+        }
+
+        impl Dispatch<$offer, ()> for Daemon {
+            fn event(
+                daemon: &mut Self,
+                offer: &$offer,
+                event: <$offer as Proxy>::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                if let $offer_mod::Event::Offer { mime_type } = event {
+                    let announced = daemon.offers.iter_mut().find(|(id, ..)| *id == offer.id());
+                    if let Some((_, _, mimes)) = announced {
+                        mimes.push(mime_type);
+                    }
+                }
+            }
+        }
+    };
+}
+
+data_control_dispatch!(
+    ExtDataControlManagerV1,
+    ExtDataControlDeviceV1,
+    ext_data_control_device_v1,
+    ExtDataControlOfferV1,
+    ext_data_control_offer_v1,
+    Offer::Ext
+);
+data_control_dispatch!(
+    ZwlrDataControlManagerV1,
+    ZwlrDataControlDeviceV1,
+    zwlr_data_control_device_v1,
+    ZwlrDataControlOfferV1,
+    zwlr_data_control_offer_v1,
+    Offer::Wlr
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn clipboard_query_needs_the_prefix_or_mode() {
+        assert_eq!(clipboard_query("clip token", "apps"), Some("token"));
+        assert_eq!(clipboard_query("token", "clipboard"), Some("token"));
+        assert_eq!(clipboard_query("clip token", "clipboard"), Some("token"));
+        assert_eq!(clipboard_query("clipboard", "apps"), None);
+        assert_eq!(clipboard_query("token", "apps"), None);
+    }
+
+    #[test]
+    fn images_come_before_text_when_kept() {
+        let ignore = strings(&DEFAULT_IGNORE);
+        let mimes = strings(&["text/plain", "image/png", "text/plain;charset=utf-8"]);
+        assert_eq!(
+            pick_mime(&mimes, true, &ignore).as_deref(),
+            Some("image/png")
+        );
+        assert_eq!(
+            pick_mime(&mimes, false, &ignore).as_deref(),
+            Some("text/plain;charset=utf-8")
+        );
+        assert_eq!(
+            pick_mime(&strings(&["STRING", "UTF8_STRING"]), true, &ignore).as_deref(),
+            Some("UTF8_STRING")
+        );
+    }
+
+    #[test]
+    fn unknown_and_ignored_offers_are_skipped() {
+        let ignore = strings(&DEFAULT_IGNORE);
+        assert_eq!(pick_mime(&strings(&["image/png"]), false, &ignore), None);
+        assert_eq!(
+            pick_mime(&strings(&["application/x-qt-image"]), true, &ignore),
+            None
+        );
+        assert_eq!(pick_mime(&[], true, &ignore), None);
+        let secret = strings(&["text/plain", "x-kde-passwordManagerHint"]);
+        assert_eq!(pick_mime(&secret, true, &ignore), None);
+        assert_eq!(pick_mime(&secret, true, &[]).as_deref(), Some("text/plain"));
+    }
+}
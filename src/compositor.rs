@@ -0,0 +1,432 @@
+use crate::utils::strip_field_codes;
+use eyre::{Result, eyre};
+use gtk4::{
+    gio::{AppInfo, DesktopAppInfo},
+    glib,
+    prelude::*,
+};
+use serde_json::Value;
+use std::env;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+// i3-ipc message types used by Sway.
+const SWAY_RUN_COMMAND: u32 = 0;
+const SWAY_GET_OUTPUTS: u32 = 3;
+const SWAY_GET_TREE: u32 = 4;
+const SWAY_MAGIC: &[u8] = b"i3-ipc";
+
+pub enum Compositor {
+    Hyprland(PathBuf),
+    Sway(PathBuf),
+}
+
+pub struct Client {
+    pub id: String,
+    pub class: String,
+}
+
+impl Compositor {
+    /// Picks the IPC backend from the "compositor" config value, "auto" probes both.
+    pub fn detect(mode: &str) -> Option<Self> {
+        match mode {
+            "none" => None,
+            "hyprland" => Self::hyprland(),
+            "sway" => Self::sway(),
+            _ => {
+                if mode != "auto" {
+                    println!(
+                        "\"{}\" isn't a valid value for \"compositor\", going with default: \"auto\".",
+                        mode
+                    );
+                }
+                Self::hyprland().or_else(Self::sway)
+            }
+        }
+    }
+
+    fn hyprland() -> Option<Self> {
+        let signature = env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+        let mut sockets = Vec::new();
+        if let Ok(runtime_dir) = env::var("XDG_RUNTIME_DIR") {
+            sockets.push(PathBuf::from(runtime_dir).join("hypr").join(&signature));
+        }
+        // Hyprland older than 0.40 kept its sockets in /tmp.
+        sockets.push(PathBuf::from("/tmp/hypr").join(&signature));
+
+        sockets
+            .into_iter()
+            .map(|dir| dir.join(".socket.sock"))
+            .find(|socket| socket.exists())
+            .map(Compositor::Hyprland)
+    }
+
+    fn sway() -> Option<Self> {
+        env::var("SWAYSOCK")
+            .ok()
+            .map(PathBuf::from)
+            .filter(|socket| socket.exists())
+            .map(Compositor::Sway)
+    }
+
+    pub fn clients(&self) -> Result<Vec<Client>> {
+        let mut clients = Vec::new();
+        match self {
+            Compositor::Hyprland(socket) => {
+                let data: Value = serde_json::from_str(&hyprland_request(socket, "j/clients")?)?;
+                for client in data.as_array().into_iter().flatten() {
+                    let id = client.get("address").and_then(|s| s.as_str());
+                    let class = client.get("class").and_then(|s| s.as_str());
+                    if let (Some(id), Some(class)) = (id, class) {
+                        clients.push(Client {
+                            id: id.to_string(),
+                            class: class.to_string(),
+                        });
+                    }
+                }
+            }
+            Compositor::Sway(socket) => {
+                let data: Value = serde_json::from_str(&sway_request(socket, SWAY_GET_TREE, "")?)?;
+                sway_collect_clients(&data, &mut clients);
+            }
+        }
+        Ok(clients)
+    }
+
+    pub fn focus(&self, client: &Client) -> Result<()> {
+        match self {
+            Compositor::Hyprland(socket) => {
+                let command = format!("dispatch focuswindow address:{}", client.id);
+                hyprland_dispatch(socket, &command)
+            }
+            Compositor::Sway(socket) => {
+                let command = format!("[con_id={}] focus", client.id);
+                sway_command(socket, &command)
+            }
+        }
+    }
+
+    pub fn exec_on_workspace(&self, command: &str, workspace: &str) -> Result<()> {
+        match self {
+            Compositor::Hyprland(socket) => {
+                let command = format!("dispatch exec [workspace {}] {}", workspace, command);
+                hyprland_dispatch(socket, &command)
+            }
+            Compositor::Sway(socket) => {
+                let command = format!(
+                    "workspace \"{}\"; {}",
+                    workspace.replace('"', "\\\""),
+                    sway_exec(command)?
+                );
+                sway_command(socket, &command)
+            }
+        }
+    }
+
+    /// Name of the output holding keyboard focus, matching `gdk::Monitor::connector()`.
+    pub fn focused_output(&self) -> Result<Option<String>> {
+        let data: Value = match self {
+            Compositor::Hyprland(socket) => {
+                serde_json::from_str(&hyprland_request(socket, "j/monitors")?)?
+            }
+            Compositor::Sway(socket) => {
+                serde_json::from_str(&sway_request(socket, SWAY_GET_OUTPUTS, "")?)?
+            }
+        };
+
+        Ok(data
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|output| output.get("focused").and_then(|f| f.as_bool()) == Some(true))
+            .and_then(|output| output.get("name").and_then(|s| s.as_str()))
+            .map(|name| name.to_string()))
+    }
+}
+
+/// Finds an open window that belongs to the app, comparing the window class
+/// against StartupWMClass, the desktop id and the executable name.
+pub fn find_client<'a>(clients: &'a [Client], appynka: &AppInfo) -> Option<&'a Client> {
+    let mut candidates = Vec::new();
+    if let Some(wm_class) = appynka
+        .downcast_ref::<DesktopAppInfo>()
+        .and_then(|desktop_info| desktop_info.startup_wm_class())
+    {
+        candidates.push(wm_class.to_lowercase());
+    }
+    if let Some(id) = appynka.id() {
+        candidates.push(id.trim_end_matches(".desktop").to_lowercase());
+    }
+    if let Some(executable) = appynka.executable().file_name() {
+        candidates.push(executable.to_string_lossy().to_lowercase());
+    }
+
+    clients
+        .iter()
+        .find(|client| candidates.contains(&client.class.to_lowercase()))
+}
+
+/// Command line suitable for handing over to the compositor's exec.
+pub fn app_command(appynka: &AppInfo) -> Option<String> {
+    let commandline = appynka.commandline()?;
+    let command = strip_field_codes(&commandline.to_string_lossy());
+    if command.is_empty() {
+        None
+    } else {
+        Some(command)
+    }
+}
+
+fn hyprland_request(socket: &Path, command: &str) -> Result<String> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.write_all(command.as_bytes())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+fn hyprland_dispatch(socket: &Path, command: &str) -> Result<()> {
+    let response = hyprland_request(socket, command)?;
+    if response.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "Hyprland refused \"{}\": {}",
+            command,
+            response.trim()
+        ))
+    }
+}
+
+fn sway_request(socket: &Path, kind: u32, payload: &str) -> Result<String> {
+    let mut stream = UnixStream::connect(socket)?;
+    let mut message = Vec::with_capacity(SWAY_MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(SWAY_MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message)?;
+
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header)?;
+    if &header[..6] != SWAY_MAGIC {
+        return Err(eyre!("Sway replied with an invalid IPC header."));
+    }
+    let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body)?;
+    Ok(String::from_utf8(body)?)
+}
+
+/// The Sway `exec` of `command`. Sway splits its commands at `;` and `,` outside
+/// quotes and hands `sh -c` an `exec` argument with only its outer quotes taken off,
+/// so each argument is double quoted for the shell and all of them single quoted.
+fn sway_exec(command: &str) -> Result<String> {
+    let mut args = Vec::new();
+    for arg in glib::shell_parse_argv(command)? {
+        let arg = arg.to_string_lossy();
+        if arg.contains('\'') {
+            return Err(eyre!(
+                "Sway can't pass the apostrophe in \"{}\" along.",
+                command
+            ));
+        }
+        let mut quoted = String::from("\"");
+        for c in arg.chars() {
+            if matches!(c, '"' | '\\' | '$' | '`') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        args.push(quoted);
+    }
+    Ok(format!("exec '{}'", args.join(" ")))
+}
+
+fn sway_command(socket: &Path, command: &str) -> Result<()> {
+    let data: Value = serde_json::from_str(&sway_request(socket, SWAY_RUN_COMMAND, command)?)?;
+    for reply in data.as_array().into_iter().flatten() {
+        if reply.get("success").and_then(|s| s.as_bool()) != Some(true) {
+            let error = reply
+                .get("error")
+                .and_then(|s| s.as_str())
+                .unwrap_or("unknown error");
+            return Err(eyre!("Sway refused \"{}\": {}", command, error));
+        }
+    }
+    Ok(())
+}
+
+fn sway_collect_clients(node: &Value, clients: &mut Vec<Client>) {
+    if node.get("pid").is_some() {
+        let class = node.get("app_id").and_then(|s| s.as_str()).or_else(|| {
+            node.get("window_properties")
+                .and_then(|p| p.get("class"))
+                .and_then(|s| s.as_str())
+        });
+        if let (Some(id), Some(class)) = (node.get("id").and_then(|i| i.as_i64()), class) {
+            clients.push(Client {
+                id: id.to_string(),
+                class: class.to_string(),
+            });
+        }
+    }
+
+    for children in ["nodes", "floating_nodes"] {
+        for child in node
+            .get(children)
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+        {
+            sway_collect_clients(child, clients);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::tests::desktop_app;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    // Answers the first request on a socket in the temp dir with `reply`, and hands
+    // back the request.
+    fn mock_socket(
+        name: &str,
+        reply: fn(&mut UnixStream) -> Vec<u8>,
+    ) -> (PathBuf, thread::JoinHandle<Vec<u8>>) {
+        let socket = env::temp_dir().join(format!(
+            "void-launcher-{}-{}.sock",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            reply(&mut stream)
+        });
+        (socket, server)
+    }
+
+    fn apps() -> Vec<AppInfo> {
+        vec![
+            desktop_app(
+                "org.mozilla.firefox.desktop",
+                &[
+                    ("Name", "Firefox"),
+                    ("Exec", "firefox %u"),
+                    ("StartupWMClass", "Navigator"),
+                ],
+            ),
+            desktop_app(
+                "org.gnome.Nautilus.desktop",
+                &[("Name", "Files"), ("Exec", "nautilus --new-window")],
+            ),
+            desktop_app("foot.desktop", &[("Name", "Foot"), ("Exec", "foot")]),
+            desktop_app(
+                "gimp.desktop",
+                &[("Name", "GIMP"), ("Exec", "gimp-2.10 %U")],
+            ),
+        ]
+    }
+
+    fn found(clients: &[Client]) -> Vec<Option<String>> {
+        apps()
+            .iter()
+            .map(|appynka| find_client(clients, appynka).map(|c| c.id.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn sway_exec_keeps_the_command_whole() {
+        let command = r#"sh -c "echo a; echo b, c" \$HOME "d\"e" '`f`'"#;
+        let exec = sway_exec(command).unwrap();
+        assert_eq!(
+            exec,
+            r#"exec '"sh" "-c" "echo a; echo b, c" "\$HOME" "d\"e" "\`f\`"'"#
+        );
+        // Sway runs what's between the outer quotes, the shell gets the same arguments.
+        let run = |command: &str| {
+            let script = format!("set -- {}; printf '%s|' \"$@\"", command);
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(script)
+                .output();
+            String::from_utf8(output.unwrap().stdout).unwrap()
+        };
+        let inner = &exec["exec '".len()..exec.len() - 1];
+        assert_eq!(run(inner), run(command));
+        assert_eq!(run(inner), "sh|-c|echo a; echo b, c|$HOME|d\"e|`f`|");
+
+        assert!(sway_exec("notify-send 'it works'").is_ok());
+        assert!(sway_exec(r#"notify-send "it's done""#).is_err());
+        assert!(sway_exec("unbalanced \"quote").is_err());
+    }
+
+    #[test]
+    fn find_client_matches_hyprland_clients() {
+        let (socket, server) = mock_socket("hyprland", |stream| {
+            let mut request = [0u8; 64];
+            let read = stream.read(&mut request).unwrap();
+            let clients = r#"[
+                {"address": "0x1", "class": "navigator"},
+                {"address": "0x2", "class": "Nautilus"},
+                {"address": "0x3", "class": "foot"},
+                {"address": "0x4"}
+            ]"#;
+            stream.write_all(clients.as_bytes()).unwrap();
+            request[..read].to_vec()
+        });
+        let clients = Compositor::Hyprland(socket.clone()).clients().unwrap();
+        assert_eq!(server.join().unwrap(), b"j/clients");
+        let _ = std::fs::remove_file(socket);
+
+        assert_eq!(
+            found(&clients),
+            [
+                Some("0x1".to_string()),
+                Some("0x2".to_string()),
+                Some("0x3".to_string()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn find_client_matches_sway_windows() {
+        let (socket, server) = mock_socket("sway", |stream| {
+            let mut header = [0u8; 14];
+            stream.read_exact(&mut header).unwrap();
+            let tree = r#"{"id": 1, "nodes": [{"id": 2, "nodes": [
+                {"id": 10, "pid": 100, "app_id": "nautilus"},
+                {"id": 11, "pid": 101, "app_id": null, "window_properties": {"class": "Navigator"}}
+            ], "floating_nodes": [{"id": 12, "pid": 102, "app_id": "Gimp-2.10"}]}]}"#;
+            let mut reply = SWAY_MAGIC.to_vec();
+            reply.extend_from_slice(&(tree.len() as u32).to_ne_bytes());
+            reply.extend_from_slice(&SWAY_GET_TREE.to_ne_bytes());
+            reply.extend_from_slice(tree.as_bytes());
+            stream.write_all(&reply).unwrap();
+            header.to_vec()
+        });
+        let clients = Compositor::Sway(socket.clone()).clients().unwrap();
+        let request = server.join().unwrap();
+        assert_eq!(&request[..6], SWAY_MAGIC);
+        assert_eq!(request[10..], SWAY_GET_TREE.to_ne_bytes());
+        let _ = std::fs::remove_file(socket);
+
+        assert_eq!(
+            found(&clients),
+            [
+                Some("11".to_string()),
+                Some("10".to_string()),
+                None,
+                Some("12".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use crate::matcher::{match_score, typo_score};
+use crate::provider::{Item, Provider};
+use crate::utils::{ConfFile, get_conf_objects, terminal_command};
+use dirs::home_dir;
+use gtk4::{
+    self as gtk, ApplicationWindow,
+    gio::{self, AppInfo, AppLaunchContext, FileIcon, Icon, ThemedIcon},
+    glib,
+    prelude::*,
+};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+
+pub enum CustomAction {
+    Command(String),
+    Url(String),
+    Shell(String),
+}
+
+/// An item of "custom-entries", shown and searched like an installed app.
+pub struct CustomEntry {
+    pub name: String,
+    pub icon: String,
+    pub keywords: Vec<String>,
+    pub action: CustomAction,
+    pub terminal: bool,
+}
+
+fn parse_entry(entry: &Map<String, Value>) -> Option<CustomEntry> {
+    let text = |key: &str| {
+        entry
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+    let name = text("name")?;
+    let action = if let Some(command) = text("command") {
+        CustomAction::Command(command)
+    } else if let Some(url) = text("url") {
+        CustomAction::Url(url)
+    } else if let Some(shell) = text("shell") {
+        CustomAction::Shell(shell)
+    } else {
+        println!(
+            "Custom entry \"{}\" needs a \"command\", \"url\" or \"shell\", skipping it.",
+            name
+        );
+        return None;
+    };
+    let keywords = entry
+        .get("keywords")
+        .and_then(|k| k.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|k| k.as_str())
+        .map(|k| k.to_string())
+        .collect();
+
+    Some(CustomEntry {
+        name,
+        icon: text("icon").unwrap_or_else(|| "application-x-executable".to_string()),
+        keywords,
+        action,
+        terminal: entry.get("terminal").and_then(|t| t.as_bool()) == Some(true),
+    })
+}
+
+pub fn load_custom_entries() -> Vec<CustomEntry> {
+    let conf = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+    conf.and_then(|conf| get_conf_objects(conf.read(), "custom-entries"))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(parse_entry)
+        .collect()
+}
+
+/// Icon names go through the icon theme, paths are loaded from the file.
+pub fn entry_icon(icon: &str) -> Icon {
+    let path = match icon.strip_prefix("~/") {
+        Some(rest) => home_dir().map(|home| home.join(rest)),
+        None if icon.starts_with('/') => Some(PathBuf::from(icon)),
+        None => None,
+    };
+    match path {
+        Some(path) => FileIcon::new(&gio::File::for_path(path)).upcast(),
+        None => ThemedIcon::new(icon).upcast(),
+    }
+}
+
+fn spawn(argv: Vec<String>, terminal: bool) {
+    let argv = if terminal {
+        let args: Vec<&str> = argv.iter().map(|s| s.as_str()).collect();
+        match terminal_command(&args) {
+            Some(argv) => argv,
+            None => {
+                eprintln!("Couldn't find a terminal, set \"terminal\" in your config file.");
+                return;
+            }
+        }
+    } else {
+        argv
+    };
+    let Some((program, args)) = argv.split_first() else {
+        return;
+    };
+    Command::new(program)
+        .args(args)
+        .spawn()
+        .map(|_| ())
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to start {}: {}", program, err);
+        });
+}
+
+fn run_entry(entry: &CustomEntry) {
+    match &entry.action {
+        CustomAction::Command(command) => match glib::shell_parse_argv(command) {
+            Ok(argv) => {
+                let argv = argv
+                    .iter()
+                    .map(|arg| arg.to_string_lossy().to_string())
+                    .collect();
+                spawn(argv, entry.terminal);
+            }
+            Err(err) => eprintln!("Failed to parse \"{}\": {}", command, err),
+        },
+        CustomAction::Shell(shell) => {
+            let argv = vec!["sh".to_string(), "-c".to_string(), shell.clone()];
+            spawn(argv, entry.terminal);
+        }
+        CustomAction::Url(url) => {
+            let context = AppLaunchContext::new();
+            AppInfo::launch_default_for_uri(url, Some(&context)).unwrap_or_else(|err| {
+                eprintln!("Failed to open {}: {}", url, err);
+            });
+        }
+    }
+}
+
+/// The "custom-entries" of the config, read again on every query so edits show up right away.
+pub struct CustomProvider {
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl CustomProvider {
+    pub fn new(window: &ApplicationWindow, app: &gtk::Application) -> Self {
+        Self {
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+}
+
+impl Provider for CustomProvider {
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    fn title(&self) -> &'static str {
+        "Custom"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let mut items = self.items(|entry| match_score(query, &entry.name, &entry.keywords));
+        for item in items.iter_mut() {
+            item.highlight(query);
+        }
+        items
+    }
+
+    fn suggest(&self, query: &str) -> Vec<Item> {
+        self.items(|entry| typo_score(query, &entry.name, &entry.keywords))
+    }
+}
+
+impl CustomProvider {
+    /// The entries `score` gives a score to.
+    fn items<F>(&self, score: F) -> Vec<Item>
+    where
+        F: Fn(&CustomEntry) -> Option<i64>,
+    {
+        let mut items = Vec::new();
+        for entry in load_custom_entries() {
+            let Some(score) = score(&entry) else {
+                continue;
+            };
+            let icon = entry_icon(&entry.icon);
+            let title = entry.name.clone();
+            let window = self.window.clone();
+            let app = self.app.clone();
+            let mut item = Item::new(
+                &title,
+                icon,
+                Rc::new(move || {
+                    window.close();
+                    run_entry(&entry);
+                    app.quit();
+                }),
+            );
+            item.score = score;
+            items.push(item);
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(json: &str) -> Option<CustomEntry> {
+        let value: Value = serde_json::from_str(json).unwrap();
+        parse_entry(value.as_object().unwrap())
+    }
+
+    #[test]
+    fn commands_keep_their_keywords_and_terminal() {
+        let htop = entry(
+            r#"{"name": "Processes", "command": "htop --tree", "terminal": true,
+                "icon": "utilities-system-monitor", "keywords": ["top", 3, "tasks"]}"#,
+        )
+        .unwrap();
+        assert_eq!(htop.name, "Processes");
+        assert!(matches!(&htop.action, CustomAction::Command(command) if command == "htop --tree"));
+        assert!(htop.terminal);
+        assert_eq!(htop.icon, "utilities-system-monitor");
+        assert_eq!(htop.keywords, ["top", "tasks"]);
+    }
+
+    #[test]
+    fn urls_and_shell_lines_are_actions_too() {
+        let mail = entry(r#"{"name": "Mail", "url": "https://mail.example.org"}"#).unwrap();
+        assert!(
+            matches!(&mail.action, CustomAction::Url(url) if url == "https://mail.example.org")
+        );
+        assert_eq!(mail.icon, "application-x-executable");
+        assert!(mail.keywords.is_empty() && !mail.terminal);
+
+        let shot =
+            entry(r#"{"name": "Shot", "shell": "grim - | wl-copy", "terminal": "yes"}"#).unwrap();
+        assert!(matches!(&shot.action, CustomAction::Shell(shell) if shell == "grim - | wl-copy"));
+        assert!(!shot.terminal);
+
+        // The command wins when there's more than one.
+        let both =
+            entry(r#"{"name": "Both", "url": "https://example.org", "command": "true"}"#).unwrap();
+        assert!(matches!(both.action, CustomAction::Command(_)));
+    }
+
+    #[test]
+    fn entries_need_a_name_and_an_action() {
+        assert!(entry(r#"{"command": "htop"}"#).is_none());
+        assert!(entry(r#"{"name": "Nothing"}"#).is_none());
+        assert!(entry(r#"{"name": "Number", "command": 42}"#).is_none());
+        assert!(entry(r#"{"name": 7, "command": "htop"}"#).is_none());
+    }
+}
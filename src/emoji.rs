@@ -0,0 +1,371 @@
+use crate::matcher::{match_score, matches};
+use crate::menu::{MenuItem, MenuItems};
+use crate::provider::{Item, ItemsFuture, Provider};
+use crate::utils::{ConfFile, get_conf_option};
+use dirs::home_dir;
+use gtk4::{
+    self as gtk, ApplicationWindow,
+    gio::{self, ThemedIcon},
+    glib,
+    prelude::*,
+};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+// Keeps the grid responsive, there are ~1900 emoji and ~40000 named characters.
+const MAX_SYMBOLS: usize = 300;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SymbolSet {
+    Emoji,
+    Unicode,
+}
+
+/// Splits the symbol prefix off the query, `:` for emoji and `::` for Unicode names.
+/// With `--mode emoji` or `--mode unicode` no prefix is needed.
+pub fn symbol_query<'a>(text: &'a str, mode: &str) -> Option<(SymbolSet, &'a str)> {
+    if let Some(rest) = text.strip_prefix("::") {
+        Some((SymbolSet::Unicode, rest))
+    } else if let Some(rest) = text.strip_prefix(':') {
+        Some((SymbolSet::Emoji, rest))
+    } else if mode == "emoji" {
+        Some((SymbolSet::Emoji, text))
+    } else if mode == "unicode" {
+        Some((SymbolSet::Unicode, text))
+    } else {
+        None
+    }
+}
+
+/// Emoji or every named Unicode character, copied or typed on activation.
+pub struct SymbolsProvider {
+    set: SymbolSet,
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl SymbolsProvider {
+    pub fn emoji(window: &ApplicationWindow, app: &gtk::Application) -> Self {
+        Self {
+            set: SymbolSet::Emoji,
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+
+    pub fn unicode(window: &ApplicationWindow, app: &gtk::Application) -> Self {
+        Self {
+            set: SymbolSet::Unicode,
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+}
+
+fn symbol_item(
+    symbol: &str,
+    name: &str,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) -> Item {
+    let delivered = symbol.to_string();
+    let window = window.clone();
+    let app = app.clone();
+    let mut item = Item::new(
+        name,
+        ThemedIcon::new("character-map").upcast(),
+        Rc::new(move || deliver_symbol(&delivered, &window, &app)),
+    );
+    item.glyph = Some(symbol.to_string());
+    item
+}
+
+impl Provider for SymbolsProvider {
+    fn name(&self) -> &'static str {
+        match self.set {
+            SymbolSet::Emoji => "emoji",
+            SymbolSet::Unicode => "unicode",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self.set {
+            SymbolSet::Emoji => "Emoji",
+            SymbolSet::Unicode => "Symbols",
+        }
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let query = query.trim();
+        match self.set {
+            SymbolSet::Emoji => search_emoji(query)
+                .into_iter()
+                .map(|emoji| {
+                    let mut item =
+                        symbol_item(emoji.as_str(), emoji.name(), &self.window, &self.app);
+                    if let Some(skin_tones) = emoji.skin_tones() {
+                        let tones: Vec<&'static str> = skin_tones.map(|e| e.as_str()).collect();
+                        let window = self.window.clone();
+                        let app = self.app.clone();
+                        item.actions = Some(Rc::new(move || {
+                            let mut items: MenuItems = Vec::new();
+                            for tone in tones.iter().copied() {
+                                let window = window.clone();
+                                let app = app.clone();
+                                items.push(MenuItem::Action(
+                                    tone.to_string(),
+                                    std::boxed::Box::new(move || {
+                                        deliver_symbol(tone, &window, &app)
+                                    }),
+                                ));
+                            }
+                            items
+                        }));
+                    }
+                    item
+                })
+                .collect(),
+            SymbolSet::Unicode => search_unicode(query)
+                .into_iter()
+                .map(|(c, name)| symbol_item(&c.to_string(), &name, &self.window, &self.app))
+                .collect(),
+        }
+    }
+
+    // The table of named characters takes a while to build and search, both
+    // happen on the gio thread pool.
+    fn query_async(&self, query: &str, _cancellable: &gio::Cancellable) -> ItemsFuture {
+        if self.set == SymbolSet::Emoji {
+            let items = self.query(query);
+            return std::boxed::Box::pin(async move { items });
+        }
+        let query = query.trim().to_string();
+        let window = self.window.clone();
+        let app = self.app.clone();
+        std::boxed::Box::pin(async move {
+            gio::spawn_blocking(move || search_unicode(&query))
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(c, name)| symbol_item(&c.to_string(), &name, &window, &app))
+                .collect()
+        })
+    }
+}
+
+fn search_emoji(query: &str) -> Vec<&'static emojis::Emoji> {
+    find_emoji(query, emoji_keywords())
+}
+
+/// Emoji matching by name, shortcode or keyword, the names first.
+fn find_emoji(query: &str, keywords: &HashMap<String, Vec<String>>) -> Vec<&'static emojis::Emoji> {
+    let mut found: Vec<(i64, &'static emojis::Emoji)> = emojis::iter()
+        .filter_map(|emoji| {
+            let mut fields: Vec<String> = emoji.shortcodes().map(|s| s.replace('_', " ")).collect();
+            if let Some(words) = keywords.get(&emoji.as_str().replace('\u{fe0f}', "")) {
+                fields.extend(words.iter().cloned());
+            }
+            Some((match_score(query, emoji.name(), &fields)?, emoji))
+        })
+        .collect();
+    found.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    found
+        .into_iter()
+        .take(MAX_SYMBOLS)
+        .map(|(_, emoji)| emoji)
+        .collect()
+}
+
+/// Keywords of the emoji from the CLDR annotations installed with the system, like
+/// "laugh" for 😂, keyed without variation selectors. Empty without them.
+fn emoji_keywords() -> &'static HashMap<String, Vec<String>> {
+    static KEYWORDS: OnceLock<HashMap<String, Vec<String>>> = OnceLock::new();
+    KEYWORDS.get_or_init(|| {
+        let mut keywords = HashMap::new();
+        let dirs = std::iter::once(glib::user_data_dir()).chain(glib::system_data_dirs());
+        for dir in dirs {
+            for annotations in ["annotations", "annotationsDerived"] {
+                let file = dir
+                    .join("unicode/cldr/common")
+                    .join(annotations)
+                    .join("en.xml");
+                if let Ok(xml) = fs::read_to_string(file) {
+                    parse_annotations(&xml, &mut keywords);
+                }
+            }
+        }
+        keywords
+    })
+}
+
+/// Adds the `|` separated keywords of a CLDR annotations file, the first file to
+/// annotate an emoji wins. The spoken names marked `type="tts"` are left out.
+fn parse_annotations(xml: &str, keywords: &mut HashMap<String, Vec<String>>) {
+    let annotation = Regex::new(r#"<annotation cp="([^"]+)">([^<]*)</annotation>"#).unwrap();
+    let unescape = |text: &str| {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    };
+    for captures in annotation.captures_iter(xml) {
+        let words = captures[2]
+            .split('|')
+            .map(|word| unescape(word.trim()))
+            .filter(|word| !word.is_empty())
+            .collect();
+        keywords
+            .entry(unescape(&captures[1]).replace('\u{fe0f}', ""))
+            .or_insert(words);
+    }
+}
+
+fn search_unicode(query: &str) -> Vec<(char, String)> {
+    let code_point = query
+        .strip_prefix("U+")
+        .or_else(|| query.strip_prefix("u+"))
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .and_then(char::from_u32);
+    if let Some(c) = code_point {
+        let name = unicode_names2::name(c)
+            .map(|name| name.to_string().to_lowercase())
+            .unwrap_or_else(|| format!("u+{:04x}", c as u32));
+        return vec![(c, name)];
+    }
+
+    unicode_table()
+        .iter()
+        .filter(|(_, name)| matches(query, name, &[]))
+        .take(MAX_SYMBOLS)
+        .cloned()
+        .collect()
+}
+
+fn unicode_table() -> &'static Vec<(char, String)> {
+    static TABLE: OnceLock<Vec<(char, String)>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        (0..=0x10FFFF)
+            .filter_map(char::from_u32)
+            .filter(|c| !c.is_control())
+            .filter_map(|c| {
+                unicode_names2::name(c).map(|name| (c, name.to_string().to_lowercase()))
+            })
+            .collect()
+    })
+}
+
+/// Copies the symbol with wl-copy or types it into the focused window with wtype,
+/// depending on "emoji-action".
+pub fn deliver_symbol(symbol: &str, window: &ApplicationWindow, app: &gtk::Application) {
+    let action = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok())
+        .map(|conf| get_conf_option(conf.read(), "emoji-action"))
+        .unwrap_or_else(|| "copy".to_string());
+
+    window.close();
+    let result = if action == "type" {
+        // Give the compositor a moment to hand keyboard focus back before typing.
+        Command::new("wtype")
+            .args(["-s", "150"])
+            .arg(symbol)
+            .spawn()
+    } else {
+        if action != "copy" {
+            println!(
+                "\"{}\" isn't a valid value for \"emoji-action\", going with default: \"copy\".",
+                action
+            );
+        }
+        Command::new("wl-copy").arg("--").arg(symbol).spawn()
+    };
+    if let Err(err) = result {
+        eprintln!("Failed to deliver \"{}\": {}", symbol, err);
+    }
+    app.quit();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbol_query_picks_the_set_by_prefix_or_mode() {
+        assert!(matches!(
+            symbol_query(":fox", "apps"),
+            Some((SymbolSet::Emoji, "fox"))
+        ));
+        assert!(matches!(
+            symbol_query("::arrow", "apps"),
+            Some((SymbolSet::Unicode, "arrow"))
+        ));
+        assert!(matches!(
+            symbol_query("fox", "emoji"),
+            Some((SymbolSet::Emoji, "fox"))
+        ));
+        assert!(matches!(
+            symbol_query("arrow", "unicode"),
+            Some((SymbolSet::Unicode, "arrow"))
+        ));
+        assert!(symbol_query("fox", "apps").is_none());
+    }
+
+    #[test]
+    fn code_points_are_looked_up_directly() {
+        assert_eq!(
+            search_unicode("U+00E9"),
+            [('é', "latin small letter e with acute".to_string())]
+        );
+        assert_eq!(
+            search_unicode("u+2192"),
+            [('→', "rightwards arrow".to_string())]
+        );
+        // Unnamed characters go by their code point.
+        assert_eq!(
+            search_unicode("U+E000"),
+            [('\u{e000}', "u+e000".to_string())]
+        );
+    }
+
+    #[test]
+    fn unicode_names_are_searched_by_every_word() {
+        let found = search_unicode("greek small alpha");
+        assert!(found.contains(&('α', "greek small letter alpha".to_string())));
+        assert!(
+            found
+                .iter()
+                .all(|(_, name)| name.contains("greek") && name.contains("alpha"))
+        );
+    }
+
+    #[test]
+    fn annotation_keywords_find_emoji() {
+        let mut keywords = HashMap::new();
+        parse_annotations(
+            concat!(
+                "<annotation cp=\"😂\">face | joy | laugh | tear</annotation>\n",
+                "<annotation cp=\"😂\" type=\"tts\">face with tears of joy</annotation>\n",
+                "<annotation cp=\"☺️\">face | outlined | relaxed | smile</annotation>\n",
+                "<annotation cp=\"🤝\">agreement | hand | handshake | meeting | shake</annotation>\n",
+            ),
+            &mut keywords,
+        );
+        assert_eq!(keywords["😂"], ["face", "joy", "laugh", "tear"]);
+        assert_eq!(keywords["☺"], ["face", "outlined", "relaxed", "smile"]);
+
+        let without = HashMap::new();
+        let names = |query, keywords| -> Vec<&str> {
+            find_emoji(query, keywords)
+                .iter()
+                .map(|e| e.as_str())
+                .collect()
+        };
+        assert!(names("laugh", &keywords).contains(&"😂"));
+        assert!(!names("laugh", &without).contains(&"😂"));
+        assert_eq!(names("agreement", &keywords), ["🤝"]);
+    }
+}
@@ -1,12 +1,17 @@
+use crate::categories::{cycle_category, highlight_category};
+use crate::compositor::{Compositor, app_command, find_client};
+use crate::menu::popup_context_menu;
+use crate::provider::{Providers, render_items, run_search};
 use crate::ui::get_object;
-use crate::utils::{ConfFile, get_conf_data};
+use crate::utils::{ConfFile, get_conf_data, get_conf_option};
+use crate::websearch::fallback_item;
 use dirs::home_dir;
 use eyre::{Ok, Result};
 use gtk4::{
     self as gtk, ApplicationWindow, Box, Builder, Button, CssProvider, Entry, EventControllerKey,
-    FlowBox, Image, Label, Orientation,
-    gdk::Key,
-    gio::{AppInfo, AppLaunchContext, DesktopAppInfo},
+    FlowBox,
+    gdk::{Key, ModifierType},
+    gio::{AppInfo, AppLaunchContext},
     glib,
     prelude::*,
 };
@@ -17,6 +22,8 @@ pub fn events(
     app: gtk::Application,
     builder: Builder,
     icon_size_memory: Rc<RefCell<i32>>,
+    mode: String,
+    providers: Rc<Providers>,
 ) -> Result<()> {
     let window: ApplicationWindow = get_object(&builder, "window")?;
     let search_bar: Entry = get_object(&builder, "search-entry")?;
@@ -26,11 +33,34 @@ pub fn events(
     let flowbox_clone = flowbox.clone();
     let window_clone = window.clone();
     let builder_clone = builder.clone();
-    key_controller.connect_key_pressed(move |_controller, keyval, _keycode, _state| {
+    let search_bar_clone = search_bar.clone();
+    // App waiting for a workspace name after Shift+Enter.
+    let workspace_prompt: Rc<RefCell<Option<AppInfo>>> = Rc::new(RefCell::new(None));
+    let workspace_prompt_clone = workspace_prompt.clone();
+    key_controller.connect_key_pressed(move |_controller, keyval, _keycode, state| {
         if keyval == Key::Escape {
+            if workspace_prompt_clone.borrow_mut().take().is_some() {
+                search_bar_clone.set_placeholder_text(None);
+                search_bar_clone.set_text("");
+                return glib::Propagation::Stop;
+            }
             exit_animations(builder_clone.clone(), app.clone())
                 .expect("Failed to execute exit animations!");
         } else if keyval == Key::Return || keyval == Key::KP_Enter {
+            let pending = workspace_prompt_clone.borrow_mut().take();
+            if let Some(appynka) = pending {
+                let workspace = search_bar_clone.text().trim().to_string();
+                search_bar_clone.set_placeholder_text(None);
+                if workspace.is_empty() {
+                    search_bar_clone.set_text("");
+                } else {
+                    window_clone.close();
+                    launch_app(&appynka, &[], Some(&workspace));
+                    app.quit();
+                }
+                return glib::Propagation::Stop;
+            }
+
             let selected = flowbox_clone.selected_children();
             if let Some(first_selected) = selected.first() {
                 if let Some(button) = first_selected
@@ -38,116 +68,144 @@ pub fn events(
                     .and_then(|w| w.downcast::<Button>().ok())
                 {
                     unsafe {
-                        if let Some(appynka) = button.data::<AppInfo>("app-info") {
-                            window_clone.close();
-                            let context = AppLaunchContext::new();
-                            appynka
-                                .read()
-                                .launch(&[], Some(&context))
-                                .unwrap_or_else(|err| {
-                                    eprintln!("Failed to launch app: {}", err);
-                                });
-                            app.quit();
+                        let app_info = button
+                            .data::<Rc<dyn Fn() -> Option<AppInfo>>>("app-info")
+                            .map(|app_info| app_info.as_ref().clone())
+                            .filter(|_| state.contains(ModifierType::SHIFT_MASK));
+                        if let Some(appynka) = app_info.and_then(|app_info| app_info()) {
+                            let placeholder = format!("Workspace for {}", appynka.name());
+                            *workspace_prompt_clone.borrow_mut() = Some(appynka);
+                            search_bar_clone.set_text("");
+                            search_bar_clone.set_placeholder_text(Some(&placeholder));
+                            search_bar_clone.grab_focus();
+                            return glib::Propagation::Stop;
+                        }
+                        if let Some(activate) = button.data::<Rc<dyn Fn()>>("activate") {
+                            let activate = activate.as_ref().clone();
+                            activate();
                         }
                     }
                 }
             }
+        } else if keyval == Key::Menu
+            || (keyval == Key::F10 && state.contains(ModifierType::SHIFT_MASK))
+        {
+            let selected = flowbox_clone.selected_children();
+            if let Some(button) = selected
+                .first()
+                .and_then(|child| child.child())
+                .and_then(|w| w.downcast::<Button>().ok())
+            {
+                popup_context_menu(&button);
+                return glib::Propagation::Stop;
+            }
         }
         glib::Propagation::Proceed
     });
     window.add_controller(key_controller);
 
-    let app_infos = AppInfo::all();
+    // Ctrl+Tab would move the focus otherwise, so it's caught before the widgets see it.
+    let category_controller = EventControllerKey::new();
+    category_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+    let builder_clone = builder.clone();
+    let workspace_prompt_clone = workspace_prompt.clone();
+    category_controller.connect_key_pressed(move |_controller, keyval, _keycode, state| {
+        let tab = keyval == Key::Tab || keyval == Key::ISO_Left_Tab;
+        if !tab
+            || !state.contains(ModifierType::CONTROL_MASK)
+            || workspace_prompt_clone.borrow().is_some()
+        {
+            return glib::Propagation::Proceed;
+        }
+        cycle_category(&builder_clone, state.contains(ModifierType::SHIFT_MASK));
+        glib::Propagation::Stop
+    });
+    window.add_controller(category_controller);
+
+    let debounce = search_debounce();
+    let pending = providers.pending().clone();
 
     search_bar.connect_changed(move |entry| {
-        let query = entry.text().to_lowercase();
-        let app_infos_clone = app_infos.clone();
-
-        while let Some(child) = flowbox.first_child() {
-            flowbox.remove(&child); // Use reference to child
+        if workspace_prompt.borrow().is_some() {
+            return;
         }
+        let text = entry.text().to_string();
+        let icon_size = *icon_size_memory.borrow();
+        highlight_category(&builder, &text);
 
-        for appynka in app_infos_clone {
-            let name_low = appynka.name().to_lowercase();
-            let mut matches = name_low.contains(&query);
-            if let Some(desktop_info) = appynka.downcast_ref::<DesktopAppInfo>() {
-                if let Some(keywords_str) = desktop_info.string("Keywords") {
-                    let keywords: Vec<String> = keywords_str
-                        .split(';')
-                        .filter(|s| !s.is_empty())
-                        .map(|s| s.to_lowercase())
-                        .collect();
-                    if keywords.iter().any(|k| k.contains(&query))
-                        || appynka.name().to_lowercase().contains(&query)
-                    {
-                        if keywords.iter().any(|k| k.contains(&query)) {
-                            matches = true;
-                        }
-                    }
+        let mut route = providers.route(&text, &mode);
+        let flowbox = flowbox.clone();
+        let window = window.clone();
+        let app = app_clone.clone();
+        let fallback = RefCell::new(route.fallback.take());
+        pending.schedule(debounce, move |cancellable| {
+            let flowbox_clone = flowbox.clone();
+            run_search(&flowbox, route, icon_size, cancellable, move || {
+                if let Some(search) = fallback.borrow_mut().take() {
+                    let item = fallback_item(search, &window, &app);
+                    render_items(&flowbox_clone, vec![item], icon_size);
                 }
-            }
-
-            if matches && appynka.should_show() {
-                let name = appynka.name();
-                let err = format!("Failed to process the icon of app: {}.", name);
-                let icon = appynka.icon().expect(&err);
+            });
+        });
+    });
 
-                let label = Label::new(Some(&name));
-                label.set_justify(gtk::Justification::Fill);
-                label.add_css_class("app-label");
-                label.set_ellipsize(pango::EllipsizeMode::End);
-                label.set_max_width_chars(5); // or whatever works for your icon size
-                label.set_wrap(false);
-                let image = Image::from_gicon(&icon);
+    Ok(())
+}
 
-                let icon_size = *icon_size_memory.borrow();
+/// Milliseconds the search waits for the next keystroke, "search-debounce" in the config.
+fn search_debounce() -> std::time::Duration {
+    let conf = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+    let debounce = conf
+        .map(|conf| get_conf_option(conf.read(), "search-debounce"))
+        .unwrap_or_default();
+    let millis = debounce.parse().unwrap_or_else(|_| {
+        println!(
+            "\"{}\" isn't a valid value for \"search-debounce\", going with default: \"50\".",
+            debounce
+        );
+        50
+    });
+    std::time::Duration::from_millis(millis)
+}
 
-                image.set_pixel_size(icon_size);
-                image.set_valign(gtk4::Align::Center);
-                image.set_halign(gtk4::Align::Center);
-                let app_box = Box::new(Orientation::Vertical, 5);
-                app_box.append(&image);
-                app_box.append(&label);
-                app_box.add_css_class("appynka");
-                let app_button = Button::builder().child(&app_box).build();
-                app_button.add_css_class("flat");
-                app_button.add_css_class("app-button");
-                unsafe {
-                    app_button.set_data("app-info", appynka.clone());
+/// Launches the app, or with a compositor backend focuses its open window
+/// ("focus-or-launch") or starts it on the requested workspace.
+pub fn launch_app(appynka: &AppInfo, uris: &[&str], workspace: Option<&str>) {
+    let conf = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+    if let Some(conf) = conf {
+        let compositor_mode = get_conf_option(conf.read(), "compositor");
+        let focus_or_launch = get_conf_option(conf.read(), "focus-or-launch");
+        if let Some(compositor) = Compositor::detect(&compositor_mode) {
+            if let Some(workspace) = workspace {
+                if let Some(command) = app_command(appynka) {
+                    match compositor.exec_on_workspace(&command, workspace) {
+                        Err(err) => eprintln!("Failed to launch on workspace: {}", err),
+                        _ => return,
+                    }
+                }
+            } else if focus_or_launch == "true" && uris.is_empty() {
+                let clients = compositor.clients().unwrap_or_else(|err| {
+                    eprintln!("Failed to list windows: {}", err);
+                    Vec::new()
+                });
+                if let Some(client) = find_client(&clients, appynka) {
+                    match compositor.focus(client) {
+                        Err(err) => eprintln!("Failed to focus window: {}", err),
+                        _ => return,
+                    }
                 }
-
-                flowbox.insert(&app_button, -1);
-
-                apps_events(appynka, app_button, builder.clone(), app_clone.clone())
-                    .expect("Failed to run app_events function.");
             }
         }
-    });
-
-    Ok(())
-}
+    }
 
-pub fn apps_events(
-    appynka: AppInfo,
-    app_button: Button,
-    builder: Builder,
-    app: gtk::Application,
-) -> Result<()> {
-    let window: ApplicationWindow = get_object(&builder, "window")?;
-    let flowbox: FlowBox = get_object(&builder, "apps-box")?;
-    let app_button_clone = app_button.clone();
-    app_button.connect_clicked(move |_| {
-        flowbox.unselect_all();
-        app_button_clone.add_css_class("selected-button");
-        window.close();
-        let context = AppLaunchContext::new();
-        appynka.launch(&[], Some(&context)).unwrap_or_else(|err| {
+    let context = AppLaunchContext::new();
+    appynka
+        .launch_uris(uris, Some(&context))
+        .unwrap_or_else(|err| {
             eprintln!("Failed to launch app: {}", err);
         });
-        app.quit();
-    });
-
-    Ok(())
 }
 
 pub fn start_animations(
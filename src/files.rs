@@ -0,0 +1,398 @@
+use crate::matcher::fuzzy_match;
+use crate::provider::{Item, ItemsFuture, Provider};
+use crate::utils::{ConfFile, get_conf_list};
+use dirs::{cache_dir, home_dir};
+use eyre::{Result, eyre};
+use gtk4::{
+    self as gtk, ApplicationWindow,
+    gio::{self, AppInfo, AppLaunchContext},
+    prelude::*,
+};
+use ignore::WalkBuilder;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+const MAX_RESULTS: usize = 100;
+// Stops a runaway walk over something like a mounted network share.
+const MAX_INDEXED: usize = 300_000;
+// How far below a root the walk goes.
+const MAX_DEPTH: usize = 16;
+const DEFAULT_EXCLUDE: [&str; 4] = ["node_modules", "target", "__pycache__", "venv"];
+
+// Flattened index shared with the background refresh.
+static PATHS: Mutex<Option<Arc<Vec<PathBuf>>>> = Mutex::new(None);
+
+/// Direct children of a directory and its mtime when they were listed.
+struct DirEntry {
+    mtime: u64,
+    children: Vec<(String, bool)>,
+}
+
+/// Splits the files prefix, `/` or `~`, off the query.
+pub fn files_query(text: &str) -> Option<&str> {
+    text.strip_prefix('/').or_else(|| text.strip_prefix('~'))
+}
+
+fn settings() -> (Vec<PathBuf>, Vec<String>) {
+    let home = home_dir().unwrap_or_default();
+    let conf = ConfFile::new(home.join(".config/void-launcher/config.json")).ok();
+    let roots = conf
+        .as_ref()
+        .and_then(|conf| get_conf_list(conf.read(), "files-roots"))
+        .map(|roots| {
+            roots
+                .iter()
+                .map(|root| match root.strip_prefix('~') {
+                    Some(rest) => home.join(rest.trim_start_matches('/')),
+                    None => PathBuf::from(root),
+                })
+                .collect()
+        })
+        .unwrap_or_else(|| vec![home.clone()]);
+    let exclude = conf
+        .as_ref()
+        .and_then(|conf| get_conf_list(conf.read(), "files-exclude"))
+        .unwrap_or_else(|| DEFAULT_EXCLUDE.iter().map(|s| s.to_string()).collect());
+
+    (roots, exclude)
+}
+
+fn index_file() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("void-launcher/files.json"))
+}
+
+fn load_index() -> HashMap<PathBuf, DirEntry> {
+    let contents = index_file().and_then(|file| fs::read_to_string(file).ok());
+    let data: Value = contents
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or(Value::Null);
+
+    let mut index = HashMap::new();
+    for (dir, entry) in data.as_object().into_iter().flatten() {
+        let mtime = entry.get("mtime").and_then(|m| m.as_u64()).unwrap_or(0);
+        let children = entry
+            .get("children")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|child| {
+                let name = child.get(0)?.as_str()?.to_string();
+                Some((name, child.get(1)?.as_bool()?))
+            })
+            .collect();
+        index.insert(PathBuf::from(dir), DirEntry { mtime, children });
+    }
+    index
+}
+
+fn save_index(index: &HashMap<PathBuf, DirEntry>) -> Result<()> {
+    let file = index_file().ok_or(eyre!("Couldn't locate the cache directory!"))?;
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut data = Map::new();
+    for (dir, entry) in index {
+        data.insert(
+            dir.to_string_lossy().to_string(),
+            json!({ "mtime": entry.mtime, "children": entry.children }),
+        );
+    }
+    let tmp = file.with_extension("json.tmp");
+    fs::write(&tmp, Value::Object(data).to_string())?;
+    fs::rename(tmp, file)?;
+    Ok(())
+}
+
+fn mtime(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+/// Whether no indexed directory changed since the index was written, one stat per
+/// directory instead of walking them all again.
+fn index_is_current(index: &HashMap<PathBuf, DirEntry>, roots: &[PathBuf]) -> bool {
+    !index.is_empty()
+        && roots.iter().all(|root| index.contains_key(root))
+        && index.iter().all(|(dir, entry)| mtime(dir) == entry.mtime)
+}
+
+/// Walks `root` through `ignore`, which applies the .gitignore files on the way, down
+/// to `max_depth` levels below it.
+fn walker(root: &Path, exclude: &[String], max_depth: usize) -> ignore::Walk {
+    let exclude = exclude.to_vec();
+    WalkBuilder::new(root)
+        .max_depth(Some(max_depth))
+        .require_git(false)
+        .filter_entry(move |entry| {
+            !exclude
+                .iter()
+                .any(|name| entry.file_name().to_string_lossy() == name.as_str())
+        })
+        .build()
+}
+
+/// Walks everything under `root` and files every entry under its parent directory.
+fn walk(
+    root: &Path,
+    exclude: &[String],
+    max_depth: usize,
+    index: &mut HashMap<PathBuf, DirEntry>,
+    count: &mut usize,
+) {
+    let dir_entry = |dir: &Path| DirEntry {
+        mtime: mtime(dir),
+        children: Vec::new(),
+    };
+    for entry in walker(root, exclude, max_depth).filter_map(|entry| entry.ok()) {
+        if *count > MAX_INDEXED {
+            return;
+        }
+        let path = entry.path();
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        if is_dir {
+            index
+                .entry(path.to_path_buf())
+                .or_insert_with(|| dir_entry(path));
+        }
+        let Some(parent) = path.parent().filter(|_| entry.depth() > 0) else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+        index
+            .entry(parent.to_path_buf())
+            .or_insert_with(|| dir_entry(parent))
+            .children
+            .push((name, is_dir));
+        *count += 1;
+    }
+}
+
+/// Lists the direct children of `dir` again.
+fn list(dir: &Path, exclude: &[String]) -> DirEntry {
+    let children = walker(dir, exclude, 1)
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            (name, entry.file_type().is_some_and(|t| t.is_dir()))
+        })
+        .collect();
+    DirEntry {
+        mtime: mtime(dir),
+        children,
+    }
+}
+
+/// Carries `dir`, `depth` levels below its root, over from `old` into `index`. A
+/// directory keeps its children while its mtime is the same, is listed again when it
+/// changed and is walked in full when it's new.
+fn refresh(
+    dir: &Path,
+    depth: usize,
+    exclude: &[String],
+    old: &HashMap<PathBuf, DirEntry>,
+    index: &mut HashMap<PathBuf, DirEntry>,
+    count: &mut usize,
+) {
+    if *count > MAX_INDEXED {
+        return;
+    }
+    let entry = match old.get(dir) {
+        Some(entry) if entry.mtime == mtime(dir) => DirEntry {
+            mtime: entry.mtime,
+            children: entry.children.clone(),
+        },
+        Some(_) if depth < MAX_DEPTH => list(dir, exclude),
+        Some(_) => DirEntry {
+            mtime: mtime(dir),
+            children: Vec::new(),
+        },
+        None => {
+            walk(dir, exclude, MAX_DEPTH - depth, index, count);
+            return;
+        }
+    };
+    *count += entry.children.len();
+    let subdirs: Vec<PathBuf> = entry
+        .children
+        .iter()
+        .filter(|(_, is_dir)| *is_dir)
+        .map(|(name, _)| dir.join(name))
+        .collect();
+    index.insert(dir.to_path_buf(), entry);
+    for subdir in subdirs {
+        refresh(&subdir, depth + 1, exclude, old, index, count);
+    }
+}
+
+/// A new index when anything changed since `old`, `None` when it's still current.
+fn build_index(old: &HashMap<PathBuf, DirEntry>) -> Option<HashMap<PathBuf, DirEntry>> {
+    let (roots, exclude) = settings();
+    if index_is_current(old, &roots) {
+        return None;
+    }
+    let mut index = HashMap::new();
+    let mut count = 0;
+    for root in roots {
+        refresh(&root, 0, &exclude, old, &mut index, &mut count);
+    }
+    Some(index)
+}
+
+fn flatten(index: &HashMap<PathBuf, DirEntry>) -> Arc<Vec<PathBuf>> {
+    Arc::new(
+        index
+            .iter()
+            .flat_map(|(dir, entry)| entry.children.iter().map(|(name, _)| dir.join(name)))
+            .collect(),
+    )
+}
+
+/// Serves searches from the cached index right away and refreshes it in the
+/// background when the mtime of any indexed directory changed. Without a cached
+/// index searches find nothing until the first walk is done.
+fn indexed_paths() -> Arc<Vec<PathBuf>> {
+    let mut paths = PATHS.lock().unwrap();
+    if let Some(paths) = paths.as_ref() {
+        return paths.clone();
+    }
+
+    let index = load_index();
+    let flat = flatten(&index);
+    std::thread::spawn(move || {
+        let Some(fresh) = build_index(&index) else {
+            return;
+        };
+        if let Err(err) = save_index(&fresh) {
+            eprintln!("Failed to save the file index: {}", err);
+        }
+        *PATHS.lock().unwrap() = Some(flatten(&fresh));
+    });
+
+    *paths = Some(flat.clone());
+    flat
+}
+
+fn search_files(query: &str, cancellable: &gio::Cancellable) -> Vec<(i64, PathBuf)> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let home = home_dir().unwrap_or_default();
+    let mut scored: Vec<(i64, &PathBuf)> = Vec::new();
+    let paths = indexed_paths();
+    for (i, path) in paths.iter().enumerate() {
+        if i % 4096 == 0 && cancellable.is_cancelled() {
+            return Vec::new();
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let relative = path.strip_prefix(&home).unwrap_or(path).to_string_lossy();
+        // Hits in the file name count double, the rest of the path only breaks ties.
+        let score = fuzzy_match(query, &name)
+            .map(|(score, _)| score * 2)
+            .or_else(|| fuzzy_match(query, &relative).map(|(score, _)| score));
+        if let Some(score) = score {
+            scored.push((score, path));
+        }
+    }
+    scored.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then_with(|| a.1.as_os_str().len().cmp(&b.1.as_os_str().len()))
+    });
+    scored
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(score, path)| (score, path.clone()))
+        .collect()
+}
+
+fn file_items(
+    found: Vec<(i64, PathBuf)>,
+    query: &str,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) -> Vec<Item> {
+    let mut items = Vec::new();
+    for (score, path) in found {
+        let content_type = if path.is_dir() {
+            "inode/directory".into()
+        } else {
+            gio::content_type_guess(Some(&path), &[]).0
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let uri = gio::File::for_path(&path).uri();
+        let window = window.clone();
+        let app = app.clone();
+        let mut item = Item::new(
+            &name,
+            gio::content_type_get_icon(&content_type),
+            Rc::new(move || {
+                window.close();
+                let context = AppLaunchContext::new();
+                AppInfo::launch_default_for_uri(&uri, Some(&context)).unwrap_or_else(|err| {
+                    eprintln!("Failed to open {}: {}", uri, err);
+                });
+                app.quit();
+            }),
+        );
+        item.subtitle = Some(path.to_string_lossy().to_string());
+        item.score = score;
+        item.highlight(query);
+        items.push(item);
+    }
+    items
+}
+
+/// Files and folders under "files-roots", searched on the gio thread pool.
+pub struct FilesProvider {
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl FilesProvider {
+    pub fn new(window: &ApplicationWindow, app: &gtk::Application) -> Self {
+        Self {
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+}
+
+impl Provider for FilesProvider {
+    fn name(&self) -> &'static str {
+        "files"
+    }
+
+    fn title(&self) -> &'static str {
+        "Files"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let found = search_files(query.trim(), &gio::Cancellable::new());
+        file_items(found, query.trim(), &self.window, &self.app)
+    }
+
+    fn query_async(&self, query: &str, cancellable: &gio::Cancellable) -> ItemsFuture {
+        let query = query.trim().to_string();
+        let cancellable = cancellable.clone();
+        let window = self.window.clone();
+        let app = self.app.clone();
+        std::boxed::Box::pin(async move {
+            let found = {
+                let query = query.clone();
+                gio::spawn_blocking(move || search_files(&query, &cancellable))
+                    .await
+                    .unwrap_or_default()
+            };
+            file_items(found, &query, &window, &app)
+        })
+    }
+}
@@ -0,0 +1,252 @@
+use crate::matcher::fold;
+use crate::utils::{ConfFile, get_conf_option};
+use dirs::{home_dir, state_dir};
+use eyre::{Result, eyre};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A choice counts half as much after two weeks.
+const HALF_LIFE_SECS: f64 = 14.0 * 24.0 * 60.0 * 60.0;
+// Enough choices for a query put its app above any name match.
+const BOOST_PER_CHOICE: f64 = 150.0;
+const MAX_BOOST: f64 = 600.0;
+// Forgotten once they weigh less than this.
+const MIN_WEIGHT: f64 = 0.05;
+const MAX_QUERIES: usize = 1000;
+
+/// Which apps were chosen for which queries, each with a weight that decays over time.
+#[derive(Default)]
+pub struct Associations {
+    queries: HashMap<String, HashMap<String, (f64, u64)>>,
+}
+
+fn associations_file() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("void-launcher/associations.json"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn decayed(weight: f64, time: u64, now: u64) -> f64 {
+    let age = now.saturating_sub(time) as f64;
+    weight * 0.5_f64.powf(age / HALF_LIFE_SECS)
+}
+
+/// "learn-choices" in the config, on unless it's "false".
+fn learning_enabled() -> bool {
+    let conf = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+    let learn = conf
+        .map(|conf| get_conf_option(conf.read(), "learn-choices"))
+        .unwrap_or_default();
+    learn != "false"
+}
+
+impl Associations {
+    /// Reads `~/.local/state/void-launcher/associations.json`, empty when it's missing.
+    pub fn load() -> Self {
+        if !learning_enabled() {
+            return Self::default();
+        }
+        let Some(contents) = associations_file().and_then(|file| fs::read_to_string(file).ok())
+        else {
+            return Self::default();
+        };
+        let data: Value = serde_json::from_str(&contents).unwrap_or(Value::Null);
+        let queries = data
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(query, apps)| {
+                let apps = apps
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(id, choice)| {
+                        let weight = choice.get("weight")?.as_f64()?;
+                        let time = choice.get("time")?.as_u64()?;
+                        Some((id.clone(), (weight, time)))
+                    })
+                    .collect();
+                (query.clone(), apps)
+            })
+            .collect();
+        Self { queries }
+    }
+
+    fn save(&self) -> Result<()> {
+        let file = associations_file().ok_or(eyre!("Couldn't locate the state directory!"))?;
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut data = Map::new();
+        for (query, apps) in &self.queries {
+            let apps: Map<String, Value> = apps
+                .iter()
+                .map(|(id, (weight, time))| (id.clone(), json!({ "weight": weight, "time": time })))
+                .collect();
+            data.insert(query.clone(), Value::Object(apps));
+        }
+        let tmp = file.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&data)?)?;
+        fs::rename(tmp, file)?;
+        Ok(())
+    }
+
+    /// How much to raise the app for the query. Choices made for the same query count
+    /// fully, those made for a longer query starting with it count by how much was typed.
+    pub fn boost(&self, query: &str, app_id: &str) -> i64 {
+        let query = fold(query);
+        if query.is_empty() {
+            return 0;
+        }
+        let now = now();
+        let boost = self
+            .queries
+            .iter()
+            .filter(|(chosen_for, _)| chosen_for.starts_with(&query))
+            .filter_map(|(chosen_for, apps)| {
+                let (weight, time) = apps.get(app_id)?;
+                let typed = query.chars().count() as f64 / chosen_for.chars().count() as f64;
+                Some(decayed(*weight, *time, now) * typed)
+            })
+            .fold(0.0, f64::max);
+        (boost * BOOST_PER_CHOICE).min(MAX_BOOST) as i64
+    }
+}
+
+/// Remembers that the app was chosen for the query, unless "learn-choices" is "false".
+pub fn record_choice(query: &str, app_id: &str) {
+    let query = fold(query);
+    if query.is_empty() || !learning_enabled() {
+        return;
+    }
+    let mut associations = Associations::load();
+    let now = now();
+    let apps = associations.queries.entry(query).or_default();
+    let (weight, time) = apps.get(app_id).copied().unwrap_or((0.0, now));
+    apps.insert(app_id.to_string(), (decayed(weight, time, now) + 1.0, now));
+
+    // Drops what has decayed away and keeps the file small.
+    for apps in associations.queries.values_mut() {
+        apps.retain(|_, (weight, time)| decayed(*weight, *time, now) >= MIN_WEIGHT);
+    }
+    associations.queries.retain(|_, apps| !apps.is_empty());
+    if associations.queries.len() > MAX_QUERIES {
+        let mut by_weight: Vec<(String, f64)> = associations
+            .queries
+            .iter()
+            .map(|(query, apps)| {
+                let weight = apps
+                    .values()
+                    .map(|(weight, time)| decayed(*weight, *time, now))
+                    .fold(0.0, f64::max);
+                (query.clone(), weight)
+            })
+            .collect();
+        by_weight.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (query, _) in by_weight.into_iter().skip(MAX_QUERIES) {
+            associations.queries.remove(&query);
+        }
+    }
+
+    associations.save().unwrap_or_else(|err| {
+        eprintln!("Failed to save the learned choices: {}", err);
+    });
+}
+
+fn variants_file() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("void-launcher/variants.json"))
+}
+
+/// Desktop ids picked over the other installs of the same app.
+pub fn preferred_variants() -> Vec<String> {
+    let Some(contents) = variants_file().and_then(|file| fs::read_to_string(file).ok()) else {
+        return Vec::new();
+    };
+    let data: Value = serde_json::from_str(&contents).unwrap_or(Value::Null);
+    data.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|id| id.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+fn save_variants(preferred: &[String]) -> Result<()> {
+    let file = variants_file().ok_or(eyre!("Couldn't locate the state directory!"))?;
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, serde_json::to_string(preferred)?)?;
+    Ok(())
+}
+
+/// Remembers `id` as the install to show, forgetting the choice between the other `variants`.
+pub fn prefer_variant(id: &str, variants: &[String]) {
+    let mut preferred = preferred_variants();
+    preferred.retain(|p| !variants.contains(p));
+    preferred.push(id.to_string());
+    save_variants(&preferred).unwrap_or_else(|err| {
+        eprintln!("Failed to save the preferred version: {}", err);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chosen(queries: &[(&str, &str, f64, u64)]) -> Associations {
+        let mut associations = Associations::default();
+        for (query, id, weight, time) in queries {
+            associations
+                .queries
+                .entry(query.to_string())
+                .or_default()
+                .insert(id.to_string(), (*weight, *time));
+        }
+        associations
+    }
+
+    #[test]
+    fn boost_halves_every_half_life() {
+        // A minute ahead, so a second passing during the test doesn't round a boost down.
+        let now = now() + 60;
+        let half_life = HALF_LIFE_SECS as u64;
+        let associations = chosen(&[
+            ("fi", "firefox.desktop", 1.0, now),
+            ("fi", "files.desktop", 1.0, now - half_life),
+            ("fi", "fish.desktop", 1.0, now - 2 * half_life),
+        ]);
+        assert_eq!(associations.boost("fi", "firefox.desktop"), 150);
+        assert_eq!(associations.boost("fi", "files.desktop"), 75);
+        assert_eq!(associations.boost("fi", "fish.desktop"), 37);
+        assert_eq!(associations.boost("fi", "other.desktop"), 0);
+    }
+
+    #[test]
+    fn boost_counts_longer_queries_by_how_much_was_typed() {
+        let now = now() + 60;
+        let associations = chosen(&[
+            ("fire", "firefox.desktop", 2.0, now),
+            ("fi", "files.desktop", 10.0, now),
+        ]);
+        assert_eq!(associations.boost("fire", "firefox.desktop"), 300);
+        assert_eq!(associations.boost("Fi", "firefox.desktop"), 150);
+        // Choices for shorter queries don't count.
+        assert_eq!(associations.boost("fire", "files.desktop"), 0);
+        assert_eq!(associations.boost("fi", "files.desktop"), MAX_BOOST as i64);
+        assert_eq!(associations.boost("", "files.desktop"), 0);
+    }
+
+    #[test]
+    fn decayed_ignores_times_in_the_future() {
+        assert_eq!(decayed(1.0, 200, 100), 1.0);
+    }
+}
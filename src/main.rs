@@ -6,11 +6,30 @@ use gtk4::{self as gtk, CssProvider, Settings, gdk::Display, prelude::*};
 use regex::Regex;
 use std::fs::{self, File};
 use std::io;
+use std::time::Instant;
 
+mod apps;
+mod cache;
+mod categories;
+mod clipboard;
+mod compositor;
 mod constants;
+mod custom;
+mod emoji;
 mod events;
+mod files;
+mod history;
+mod matcher;
+mod menu;
+mod open;
+mod power;
+mod provider;
+mod recent;
+mod script;
+mod ssh;
 mod ui;
 mod utils;
+mod websearch;
 
 fn is_valid_hex_color(color: &str) -> bool {
     let re = Regex::new(r"^#[0-9a-fA-F]{6}$").unwrap();
@@ -57,13 +76,66 @@ pub fn is_dark_theme_active() -> bool {
     theme
 }
 
+struct Options {
+    mode: String,
+    clipboard_daemon: bool,
+    // Prints how long the first frame took, then quits.
+    benchmark_startup: bool,
+}
+
+/// Reads our own command line options, GTK gets only the program name.
+fn parse_args(args: &[String]) -> Options {
+    let mut options = Options {
+        mode: "apps".to_string(),
+        clipboard_daemon: false,
+        benchmark_startup: false,
+    };
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--mode" {
+            match args.next().map(|s| s.as_str()) {
+                Some(value @ ("apps" | "emoji" | "unicode" | "clipboard" | "recent")) => {
+                    options.mode = value.to_string()
+                }
+                Some(value) => println!(
+                    "\"{}\" isn't a valid mode, going with default: \"apps\".",
+                    value
+                ),
+                None => {
+                    println!("\"--mode\" needs a value: apps, emoji, unicode, clipboard or recent.")
+                }
+            }
+        } else if arg == "--clipboard-daemon" {
+            options.clipboard_daemon = true;
+        } else if arg == "--benchmark-startup" {
+            options.benchmark_startup = true;
+        } else {
+            println!("Unknown option \"{}\", ignoring it.", arg);
+        }
+    }
+    options
+}
+
 fn main() -> io::Result<()> {
+    let started = Instant::now();
+    let args: Vec<String> = std::env::args().collect();
+    let options = parse_args(&args);
+    if options.clipboard_daemon {
+        files_init()?;
+        if let Err(err) = clipboard::run_daemon() {
+            eprintln!("Clipboard daemon stopped: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mode = options.mode;
+    let benchmark = options.benchmark_startup.then_some(started);
     let application = gtk::Application::builder()
         .application_id("com.void-launcher.com")
         .build();
     files_init()?;
 
-    application.connect_startup(|app| {
+    application.connect_startup(move |app| {
         let provider = CssProvider::new();
         let home = home_dir().expect("Failed to determine home directory!");
 
@@ -144,10 +216,10 @@ fn main() -> io::Result<()> {
             gtk::STYLE_PROVIDER_PRIORITY_USER,
         );
 
-        build_ui(app, css, provider).expect("Failed to build UI!");
+        build_ui(app, css, provider, mode.clone(), benchmark).expect("Failed to build UI!");
     });
 
-    application.run();
+    application.run_with_args(&args[..1]);
 
     Ok(())
 }
@@ -0,0 +1,517 @@
+use deunicode::{deunicode_char, deunicode_with_tofu};
+use gtk4::{
+    gio::{AppInfo, DesktopAppInfo},
+    prelude::*,
+};
+
+/// Lowercase with diacritics folded and other scripts transliterated to ASCII, so
+/// "Kalkulačka" becomes "kalkulacka" and "Файлы" becomes "faily".
+pub fn fold(text: &str) -> String {
+    deunicode_with_tofu(text, "")
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Transliterated CJK comes out a syllable per word, "wenjian" should match "wen jian" too.
+fn folded_forms(text: &str) -> Vec<String> {
+    let folded = fold(text);
+    if text.is_ascii() {
+        vec![folded]
+    } else {
+        let compact = folded.replace(' ', "");
+        vec![folded, compact]
+    }
+}
+
+fn contains_folded(text: &str, query: &str) -> bool {
+    folded_forms(text).iter().any(|form| form.contains(query))
+}
+
+/// Field prefixes a query term can be limited to, like `name:firefox`.
+const FIELDS: [&str; 7] = [
+    "name", "keywords", "generic", "exec", "comment", "cat", "source",
+];
+
+/// One word or quoted phrase of a query.
+pub struct Term {
+    // Folded text of the term.
+    pub text: String,
+    // One of `FIELDS`, the term matches anywhere without it.
+    pub field: Option<&'static str>,
+    // `-term`, the result must not match it.
+    pub negated: bool,
+}
+
+/// Splits a query into terms on whitespace. `"text editor"` keeps a phrase together,
+/// `-term` excludes results and `field:term` looks at a single field.
+pub fn parse_query(query: &str) -> Vec<Term> {
+    let mut terms = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        let mut quoted = false;
+        while let Some(&c) = chars.peek() {
+            if c == '"' {
+                quoted = !quoted;
+            } else if c.is_whitespace() && !quoted {
+                break;
+            } else {
+                word.push(c);
+            }
+            chars.next();
+        }
+
+        let (negated, rest) = match word.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, word.as_str()),
+        };
+        let (field, text) = match rest.split_once(':') {
+            Some((field, text)) if !text.is_empty() => match FIELDS.iter().find(|f| **f == field) {
+                Some(field) => (Some(*field), text),
+                None => (None, rest),
+            },
+            _ => (None, rest),
+        };
+        let text = fold(text);
+        if !text.is_empty() {
+            terms.push(Term {
+                text,
+                field,
+                negated,
+            });
+        }
+    }
+    terms
+}
+
+/// Every term that isn't negated has to hit, the weakest hit gives the score. A query
+/// of plain words is also tried as one phrase, so "visual studio code" ranks as a name prefix.
+pub fn score_query<F>(query: &str, score: F) -> Option<i64>
+where
+    F: Fn(&Term) -> Option<i64>,
+{
+    let terms = parse_query(query);
+    // Typed text that folds to nothing, like an emoji, can't match any name.
+    if terms.is_empty() && !query.trim().is_empty() {
+        return None;
+    }
+    let mut total: Option<i64> = None;
+    let mut failed = false;
+    for term in &terms {
+        match (score(term), term.negated) {
+            (Some(_), true) => return None,
+            (None, true) => {}
+            (Some(hit), false) => total = Some(total.map_or(hit, |t| t.min(hit))),
+            (None, false) => failed = true,
+        }
+    }
+
+    let plain = terms.len() > 1 && terms.iter().all(|t| t.field.is_none() && !t.negated);
+    let phrase = plain
+        .then(|| {
+            score(&Term {
+                text: fold(query),
+                field: None,
+                negated: false,
+            })
+        })
+        .flatten();
+    if failed {
+        return phrase;
+    }
+    // Nothing but exclusions, or an empty query, matches everything left.
+    phrase.max(Some(total.unwrap_or(300)))
+}
+
+/// Case and diacritic insensitive match of every query term against a name and its keywords.
+pub fn matches(query: &str, name: &str, keywords: &[String]) -> bool {
+    match_score(query, name, keywords).is_some()
+}
+
+/// Ranks a `matches` hit: name prefix first, then anywhere in the name, then keywords only.
+pub fn match_score(query: &str, name: &str, keywords: &[String]) -> Option<i64> {
+    score_query(query, |term| match term.field {
+        None => term_score(&term.text, name, keywords),
+        Some("name") => term_score(&term.text, name, &[]),
+        Some("keywords") => keywords
+            .iter()
+            .any(|k| contains_folded(k, &term.text))
+            .then_some(100),
+        Some(_) => None,
+    })
+}
+
+fn term_score(text: &str, name: &str, keywords: &[String]) -> Option<i64> {
+    let names = folded_forms(name);
+    if names.iter().any(|name| name.starts_with(text)) {
+        Some(300)
+    } else if names.iter().any(|name| name.contains(text)) {
+        Some(200)
+    } else if keywords.iter().any(|k| contains_folded(k, text)) {
+        Some(100)
+    } else {
+        None
+    }
+}
+
+/// Optimal string alignment distance, Levenshtein plus swapping two neighbouring chars.
+fn typo_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Scores a near miss for queries nothing matched, like "thunderbrid" or "libreofice".
+/// The query is compared with each word of the name and keywords, and with the start
+/// of the whole name. Fewer typos score higher, short queries allow only one.
+pub fn typo_score(query: &str, name: &str, keywords: &[String]) -> Option<i64> {
+    let query: Vec<char> = fold(query).chars().filter(|c| *c != ' ').collect();
+    if query.len() < 3 {
+        return None;
+    }
+    let allowed = match query.len() {
+        0..=4 => 1,
+        5..=8 => 2,
+        _ => 3,
+    };
+
+    let mut candidates: Vec<Vec<char>> = Vec::new();
+    for text in std::iter::once(name).chain(keywords.iter().map(|k| k.as_str())) {
+        let folded = fold(text);
+        candidates.extend(folded.split(' ').map(|word| word.chars().collect()));
+        // "libreofice" against "libreoffice writer" looks at "libreoffice" and its neighbours.
+        let compact: Vec<char> = folded.chars().filter(|c| *c != ' ').collect();
+        for len in query.len().saturating_sub(1)..=query.len() + 1 {
+            if len <= compact.len() {
+                candidates.push(compact[..len].to_vec());
+            }
+        }
+    }
+    let distance = candidates
+        .iter()
+        .map(|candidate| typo_distance(&query, candidate))
+        .min()?;
+    (distance <= allowed).then(|| 100 - distance as i64 * 10)
+}
+
+// Below every name and keyword hit, so they only decide among apps the name didn't match.
+const GENERIC_NAME_SCORE: i64 = 80;
+const EXECUTABLE_SCORE: i64 = 70;
+const COMMENT_SCORE: i64 = 50;
+
+/// `match_score` on the app's name and keywords, then its `GenericName`, the basename of
+/// `Exec`, `StartupWMClass` and `Comment`, so "browser" or "code" find apps too.
+/// The untranslated `Name` counts as much as the translated one. Terms for any other
+/// field, like `cat:`, go to `field`.
+pub fn app_score<F>(query: &str, appynka: &AppInfo, field: F) -> Option<i64>
+where
+    F: Fn(&Term) -> Option<i64>,
+{
+    let desktop_info = appynka.downcast_ref::<DesktopAppInfo>();
+    let name = appynka.name().to_string();
+    let untranslated = desktop_info.and_then(|d| d.string("Name"));
+    let keywords = app_keywords(appynka);
+    let generic_name = desktop_info.and_then(|d| d.generic_name());
+    let executable = appynka
+        .executable()
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string());
+    let wm_class = desktop_info.and_then(|d| d.startup_wm_class());
+    let comment = appynka.description();
+
+    let hit =
+        |text: Option<&str>, term: &Term| text.is_some_and(|t| contains_folded(t, &term.text));
+    let name_score = |term: &Term, keywords: &[String]| {
+        let untranslated = untranslated
+            .as_ref()
+            .and_then(|u| term_score(&term.text, u, keywords));
+        term_score(&term.text, &name, keywords).max(untranslated)
+    };
+    let generic = |term: &Term| hit(generic_name.as_deref(), term).then_some(GENERIC_NAME_SCORE);
+    let exec = |term: &Term| {
+        (hit(executable.as_deref(), term) || hit(wm_class.as_deref(), term))
+            .then_some(EXECUTABLE_SCORE)
+    };
+    let description = |term: &Term| hit(comment.as_deref(), term).then_some(COMMENT_SCORE);
+
+    score_query(query, |term| match term.field {
+        None => name_score(term, &keywords)
+            .or_else(|| generic(term))
+            .or_else(|| exec(term))
+            .or_else(|| description(term)),
+        Some("name") => name_score(term, &[]),
+        Some("keywords") => keywords
+            .iter()
+            .any(|k| contains_folded(k, &term.text))
+            .then_some(100),
+        Some("generic") => generic(term),
+        Some("exec") => exec(term),
+        Some("comment") => description(term),
+        Some(_) => field(term),
+    })
+}
+
+pub fn app_keywords(appynka: &AppInfo) -> Vec<String> {
+    appynka
+        .downcast_ref::<DesktopAppInfo>()
+        .and_then(|desktop_info| desktop_info.string("Keywords"))
+        .map(|keywords_str| {
+            keywords_str
+                .split(';')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Subsequence match in the spirit of fzf. Returns a score, higher is better, and the
+/// char positions in `text` that matched, `None` when not every query char was found.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(fold_char)
+        .collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars.iter().map(|c| fold_char(*c)).collect();
+
+    // Try every occurrence of the first query char as a start and keep the best run.
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in (0..lower.len()).filter(|&i| lower[i] == query[0]) {
+        let mut positions = vec![start];
+        let mut next = start + 1;
+        for wanted in &query[1..] {
+            match (next..lower.len()).find(|&i| lower[i] == *wanted) {
+                Some(found) => {
+                    positions.push(found);
+                    next = found + 1;
+                }
+                None => break,
+            }
+        }
+        if positions.len() != query.len() {
+            // Later starts can't find more chars than this one did.
+            break;
+        }
+
+        let mut score = 0;
+        for (n, &pos) in positions.iter().enumerate() {
+            score += 16;
+            if is_word_start(&chars, pos) {
+                score += 12;
+            }
+            if n > 0 {
+                let gap = (pos - positions[n - 1] - 1) as i64;
+                if gap == 0 {
+                    score += 8;
+                } else {
+                    score -= gap.min(10);
+                }
+            }
+        }
+        score -= (start as i64).min(15);
+
+        if best
+            .as_ref()
+            .is_none_or(|(best_score, _)| score > *best_score)
+        {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+/// Char positions of `text` to highlight for the query, for every term that can match
+/// a name the first run matching it as a whole or else the fuzzy match.
+pub fn match_positions(query: &str, text: &str) -> Vec<usize> {
+    let folded: Vec<char> = text.chars().map(fold_char).collect();
+    let mut positions = Vec::new();
+    let terms = parse_query(query);
+    let terms = terms
+        .iter()
+        .filter(|t| !t.negated && t.field.is_none_or(|f| f == "name"));
+    for term in terms {
+        let wanted: Vec<char> = term.text.chars().collect();
+        match folded
+            .windows(wanted.len())
+            .position(|w| w == wanted.as_slice())
+        {
+            Some(start) => positions.extend(start..start + wanted.len()),
+            None => positions.extend(
+                fuzzy_match(&term.text, text)
+                    .map(|(_, found)| found)
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+    positions.sort_unstable();
+    positions.dedup();
+    positions
+}
+
+// One char for one char, so the matched positions still point into the original text.
+fn fold_char(c: char) -> char {
+    deunicode_char(c)
+        .and_then(|folded| folded.chars().next())
+        .unwrap_or(c)
+        .to_lowercase()
+        .next()
+        .unwrap_or(c)
+}
+
+fn is_word_start(chars: &[char], pos: usize) -> bool {
+    if pos == 0 {
+        return true;
+    }
+    let prev = chars[pos - 1];
+    !prev.is_alphanumeric() || (prev.is_lowercase() && chars[pos].is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_strips_diacritics_and_transliterates() {
+        assert_eq!(fold("Kalkulačka"), "kalkulacka");
+        assert_eq!(fold("Файлы"), "faily");
+        assert_eq!(fold("  Text   Editor "), "text editor");
+        assert_eq!(fold("🦊"), "fox face");
+        assert_eq!(fold("\u{e000}"), "");
+    }
+
+    #[test]
+    fn match_positions_point_into_the_original_text() {
+        assert_eq!(match_positions("kalk", "Kalkulačka"), [0, 1, 2, 3]);
+        assert_eq!(match_positions("cka", "Kalkulačka"), [7, 8, 9]);
+        assert_eq!(
+            match_positions("fire fox", "Firefox"),
+            [0, 1, 2, 3, 4, 5, 6]
+        );
+        // Excluded terms and other fields aren't in the name.
+        assert_eq!(match_positions("fox -fire exec:fire", "Firefox"), [4, 5, 6]);
+        // The fuzzy match when no run matches.
+        assert_eq!(match_positions("ffx", "Firefox"), [0, 4, 6]);
+    }
+
+    #[test]
+    fn parse_query_splits_fields_phrases_and_exclusions() {
+        let terms = parse_query(r#"name:Fire "text editor" -web foo:bar"#);
+        let parsed: Vec<(&str, Option<&str>, bool)> = terms
+            .iter()
+            .map(|t| (t.text.as_str(), t.field, t.negated))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("fire", Some("name"), false),
+                ("text editor", None, false),
+                ("web", None, true),
+                ("foo:bar", None, false),
+            ]
+        );
+        // A lone dash or a field without text is just text.
+        let parsed: Vec<(String, bool)> = parse_query("- cat:")
+            .into_iter()
+            .map(|t| (t.text, t.negated))
+            .collect();
+        assert_eq!(
+            parsed,
+            [("-".to_string(), false), ("cat:".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn score_query_takes_the_weakest_hit() {
+        let score = |term: &Term| match term.text.as_str() {
+            "fire" => Some(300),
+            "fox" => Some(200),
+            _ => None,
+        };
+        assert_eq!(score_query("fire fox", score), Some(200));
+        assert_eq!(score_query("fire -fox", score), None);
+        assert_eq!(score_query("fire -web", score), Some(300));
+        assert_eq!(score_query("fire web", score), None);
+        // Nothing but exclusions, or nothing at all, matches everything left.
+        assert_eq!(score_query("-web", score), Some(300));
+        assert_eq!(score_query("", score), Some(300));
+        // Unless the query folded away to nothing.
+        assert_eq!(score_query("\u{e000}", score), None);
+    }
+
+    #[test]
+    fn score_query_tries_plain_words_as_a_phrase() {
+        let keywords: Vec<String> = Vec::new();
+        assert_eq!(
+            match_score("visual studio code", "Visual Studio Code", &keywords),
+            Some(300)
+        );
+        assert_eq!(
+            match_score("studio code", "Visual Studio Code", &keywords),
+            Some(200)
+        );
+        assert_eq!(
+            match_score("code vim", "Visual Studio Code", &keywords),
+            None
+        );
+    }
+
+    #[test]
+    fn typo_distance_counts_swaps_as_one() {
+        let chars = |s: &str| s.chars().collect::<Vec<char>>();
+        assert_eq!(typo_distance(&chars("firefox"), &chars("firefox")), 0);
+        assert_eq!(
+            typo_distance(&chars("thunderbrid"), &chars("thunderbird")),
+            1
+        );
+        assert_eq!(
+            typo_distance(&chars("libreofice"), &chars("libreoffice")),
+            1
+        );
+        assert_eq!(typo_distance(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(typo_distance(&chars(""), &chars("abc")), 3);
+    }
+
+    #[test]
+    fn typo_score_allows_more_typos_for_longer_queries() {
+        let keywords = vec!["Mail".to_string()];
+        assert_eq!(
+            typo_score("thunderbrid", "Thunderbird", &keywords),
+            Some(90)
+        );
+        assert_eq!(
+            typo_score("libreofice", "LibreOffice Writer", &[]),
+            Some(90)
+        );
+        assert_eq!(typo_score("mial", "Thunderbird", &keywords), Some(90));
+        // Short queries get one typo and the shortest none at all.
+        assert_eq!(typo_score("maxl", "Thunderbird", &keywords), Some(90));
+        assert_eq!(typo_score("mxxl", "Thunderbird", &keywords), None);
+        assert_eq!(typo_score("ml", "Mail", &[]), None);
+    }
+}
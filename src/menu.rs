@@ -0,0 +1,116 @@
+use gtk4::{
+    self as gtk, Box, Button, EventSequenceState, GestureClick, Label, Orientation, Popover, Stack,
+    StackTransitionType, gdk, glib, prelude::*,
+};
+use std::rc::Rc;
+
+pub enum MenuItem {
+    Action(String, std::boxed::Box<dyn Fn()>),
+    // Opens its items in place of the menu, with a way back.
+    Submenu(String, MenuItems),
+}
+
+pub type MenuItems = Vec<MenuItem>;
+
+/// Gives `button` a context menu, opened by right-click or with `popup_context_menu`.
+/// The items are built when the menu opens.
+pub fn attach_context_menu<F>(button: &Button, items: F)
+where
+    F: Fn() -> MenuItems + 'static,
+{
+    let button_clone = button.clone();
+    let popup: Rc<dyn Fn()> = Rc::new(move || show_menu(&button_clone, items()));
+
+    let gesture = GestureClick::new();
+    gesture.set_button(gdk::BUTTON_SECONDARY);
+    let popup_clone = popup.clone();
+    gesture.connect_pressed(move |gesture, _, _, _| {
+        gesture.set_state(EventSequenceState::Claimed);
+        popup_clone();
+    });
+    button.add_controller(gesture);
+
+    unsafe {
+        button.set_data("context-menu", popup);
+    }
+}
+
+pub fn popup_context_menu(button: &Button) {
+    let popup = unsafe {
+        button
+            .data::<Rc<dyn Fn()>>("context-menu")
+            .map(|popup| popup.as_ref().clone())
+    };
+    if let Some(popup) = popup {
+        popup();
+    }
+}
+
+fn menu_button(text: &str) -> Button {
+    let label = Label::new(Some(text));
+    label.set_halign(gtk::Align::Start);
+    let button = Button::builder().child(&label).build();
+    button.add_css_class("flat");
+    button.add_css_class("menu-button");
+    button
+}
+
+/// A button showing the page `name` of the menu.
+fn page_button(text: &str, stack: &Stack, name: &str) -> Button {
+    let button = menu_button(text);
+    let stack = stack.downgrade();
+    let name = name.to_string();
+    button.connect_clicked(move |_| {
+        if let Some(stack) = stack.upgrade() {
+            stack.set_visible_child_name(&name);
+        }
+    });
+    button
+}
+
+/// Adds the items as the page `name`, and their submenus as pages of their own.
+fn add_page(stack: &Stack, popover: &Popover, name: &str, back: Option<&str>, items: MenuItems) {
+    let page = Box::new(Orientation::Vertical, 2);
+    stack.add_named(&page, Some(name));
+    if let Some(back) = back {
+        page.append(&page_button("‹ Back", stack, back));
+    }
+    for (index, item) in items.into_iter().enumerate() {
+        match item {
+            MenuItem::Action(text, action) => {
+                let button = menu_button(&text);
+                let popover_clone = popover.clone();
+                button.connect_clicked(move |_| {
+                    popover_clone.popdown();
+                    action();
+                });
+                page.append(&button);
+            }
+            MenuItem::Submenu(text, items) => {
+                let submenu = format!("{}-{}", name, index);
+                page.append(&page_button(&format!("{} ›", text), stack, &submenu));
+                add_page(stack, popover, &submenu, Some(name), items);
+            }
+        }
+    }
+}
+
+fn show_menu(button: &Button, items: MenuItems) {
+    if items.is_empty() {
+        return;
+    }
+
+    let popover = Popover::new();
+    popover.add_css_class("context-menu");
+    let stack = Stack::new();
+    stack.set_vhomogeneous(false);
+    stack.set_transition_type(StackTransitionType::SlideLeftRight);
+    add_page(&stack, &popover, "menu", None, items);
+    popover.set_child(Some(&stack));
+    popover.set_parent(button);
+    popover.connect_closed(|popover| {
+        let popover = popover.clone();
+        glib::idle_add_local_once(move || popover.unparent());
+    });
+    popover.popup();
+}
@@ -0,0 +1,327 @@
+use crate::events::launch_app;
+use crate::provider::{Item, Provider};
+use dirs::home_dir;
+use gtk4::{
+    self as gtk, ApplicationWindow, Entry,
+    gio::{self, AppInfo, AppLaunchContext, ThemedIcon},
+    prelude::*,
+};
+use regex::Regex;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::LazyLock;
+
+const MAX_COMPLETIONS: usize = 60;
+// Bare domains are only recognised with `www.`, a path or one of these, otherwise
+// "notes.txt" would turn into a website. TLDs that double as file extensions,
+// like "rs" or "pl", are left out so "main.rs" stays a file name.
+const COMMON_TLDS: [&str; 22] = [
+    "com", "org", "net", "io", "dev", "app", "edu", "gov", "info", "me", "co", "uk", "de", "cz",
+    "sk", "eu", "fr", "nl", "ru", "jp", "us", "ca",
+];
+
+static SCHEME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z][a-zA-Z0-9+.-]*://\S+$").unwrap());
+static MAILTO: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^mailto:[^@\s]+@[^@\s]+$").unwrap());
+static DOMAIN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([a-zA-Z0-9-]+\.)+([a-zA-Z]{2,})(:\d+)?(/\S*)?$").unwrap());
+
+/// Turns a URL, a bare domain, a `mailto:` address or an existing path into a URI.
+pub fn uri_target(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+
+    if SCHEME.is_match(text) || MAILTO.is_match(text) {
+        return Some(text.to_string());
+    }
+    if let Some(captures) = DOMAIN.captures(text) {
+        let tld = captures.get(2).map(|m| m.as_str().to_lowercase())?;
+        if text.starts_with("www.")
+            || captures.get(4).is_some()
+            || COMMON_TLDS.contains(&tld.as_str())
+        {
+            return Some(format!("https://{}", text));
+        }
+    }
+
+    let path = expand_path(text)?;
+    if path.exists() {
+        return Some(gio::File::for_path(path).uri().to_string());
+    }
+    None
+}
+
+fn expand_path(text: &str) -> Option<PathBuf> {
+    if let Some(rest) = text.strip_prefix("~/") {
+        Some(home_dir()?.join(rest))
+    } else if text == "~" {
+        home_dir()
+    } else if text.starts_with('/') {
+        Some(PathBuf::from(text))
+    } else {
+        None
+    }
+}
+
+/// Queries like `~/Doc` or `/etc/` are paths to complete. A single `/word` or `~word`
+/// stays a files search until a second `/` is typed.
+pub fn path_query(text: &str) -> Option<PathBuf> {
+    let path = expand_path(text)?;
+    if text.starts_with("~/") || text[1..].contains('/') {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn content_type(uri: &str) -> String {
+    let file = gio::File::for_uri(uri);
+    match file.uri_scheme().as_deref() {
+        Some("file") => match file.path() {
+            Some(path) if path.is_dir() => "inode/directory".to_string(),
+            Some(path) => gio::content_type_guess(Some(&path), &[]).0.to_string(),
+            None => "application/octet-stream".to_string(),
+        },
+        Some(scheme) => format!("x-scheme-handler/{}", scheme),
+        None => "application/octet-stream".to_string(),
+    }
+}
+
+// Above anything else when results are blended, typing a URL means opening it.
+const OPEN_SCORE: i64 = 1000;
+
+/// "Open" with the default handler followed by every other app that can handle the URI.
+fn open_items(uri: &str, window: &ApplicationWindow, app: &gtk::Application) -> Vec<Item> {
+    let content_type = content_type(uri);
+    let default_app = AppInfo::default_for_type(&content_type, false);
+    let icon = default_app
+        .as_ref()
+        .and_then(|appynka| appynka.icon())
+        .unwrap_or_else(|| ThemedIcon::new("document-open").upcast());
+    let short = uri.trim_start_matches("file://");
+    let uri_clone = uri.to_string();
+    let window_clone = window.clone();
+    let app_clone = app.clone();
+    let mut item = Item::new(
+        &format!("Open {}", short),
+        icon,
+        Rc::new(move || {
+            window_clone.close();
+            let context = AppLaunchContext::new();
+            AppInfo::launch_default_for_uri(&uri_clone, Some(&context)).unwrap_or_else(|err| {
+                eprintln!("Failed to open {}: {}", uri_clone, err);
+            });
+            app_clone.quit();
+        }),
+    );
+    item.subtitle = Some(uri.to_string());
+    item.score = OPEN_SCORE + 1;
+    let mut items = vec![item];
+
+    let default_id = default_app.and_then(|appynka| appynka.id());
+    for appynka in AppInfo::all_for_type(&content_type) {
+        if appynka.id() == default_id || !appynka.should_show() {
+            continue;
+        }
+        let icon = appynka
+            .icon()
+            .unwrap_or_else(|| ThemedIcon::new("application-x-executable").upcast());
+        let text = format!("Open with {}", appynka.name());
+        let uri_clone = uri.to_string();
+        let window = window.clone();
+        let app = app.clone();
+        let mut item = Item::new(
+            &text,
+            icon,
+            Rc::new(move || {
+                window.close();
+                launch_app(&appynka, &[&uri_clone], None);
+                app.quit();
+            }),
+        );
+        item.subtitle = Some(uri.to_string());
+        item.score = OPEN_SCORE;
+        items.push(item);
+    }
+    items
+}
+
+/// URLs, `mailto:` addresses and existing paths typed into the search bar.
+pub struct OpenProvider {
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl OpenProvider {
+    pub fn new(window: &ApplicationWindow, app: &gtk::Application) -> Self {
+        Self {
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+}
+
+impl Provider for OpenProvider {
+    fn name(&self) -> &'static str {
+        "open"
+    }
+
+    fn title(&self) -> &'static str {
+        "Open"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        uri_target(query)
+            .map(|uri| open_items(&uri, &self.window, &self.app))
+            .unwrap_or_default()
+    }
+}
+
+/// Completes the directory entries of the path being typed, choosing a folder
+/// completes the query. An existing path can be opened as well.
+pub struct PathProvider {
+    entry: Entry,
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl PathProvider {
+    pub fn new(entry: &Entry, window: &ApplicationWindow, app: &gtk::Application) -> Self {
+        Self {
+            entry: entry.clone(),
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+
+    fn completion(&self, name: &str, full: PathBuf, is_dir: bool, typed_dir: &str) -> Item {
+        let content_type = if is_dir {
+            "inode/directory".into()
+        } else {
+            gio::content_type_guess(Some(&full), &[]).0
+        };
+        let activate: Rc<dyn Fn()> = if is_dir {
+            let completed = format!("{}{}/", typed_dir, name);
+            let entry = self.entry.clone();
+            Rc::new(move || {
+                entry.set_text(&completed);
+                entry.set_position(-1);
+                entry.grab_focus();
+            })
+        } else {
+            let uri = gio::File::for_path(&full).uri();
+            let window = self.window.clone();
+            let app = self.app.clone();
+            Rc::new(move || {
+                window.close();
+                let context = AppLaunchContext::new();
+                AppInfo::launch_default_for_uri(&uri, Some(&context)).unwrap_or_else(|err| {
+                    eprintln!("Failed to open {}: {}", uri, err);
+                });
+                app.quit();
+            })
+        };
+        let mut item = Item::new(name, gio::content_type_get_icon(&content_type), activate);
+        item.subtitle = Some(full.to_string_lossy().to_string());
+        item
+    }
+}
+
+impl Provider for PathProvider {
+    fn name(&self) -> &'static str {
+        "path"
+    }
+
+    fn title(&self) -> &'static str {
+        "Path"
+    }
+
+    fn query(&self, text: &str) -> Vec<Item> {
+        let Some(path) = path_query(text) else {
+            return Vec::new();
+        };
+        let mut items = Vec::new();
+        if path.exists() {
+            let uri = gio::File::for_path(&path).uri();
+            items.extend(open_items(&uri, &self.window, &self.app));
+        }
+
+        // "~/Doc" completes inside "~/" with "Doc" as the typed prefix.
+        let (typed_dir, typed_name) = match text.rfind('/') {
+            Some(slash) => text.split_at(slash + 1),
+            None => return items,
+        };
+        let dir = if text.ends_with('/') {
+            path.clone()
+        } else {
+            path.parent().map(|p| p.to_path_buf()).unwrap_or_default()
+        };
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            return items;
+        };
+
+        let typed_name = typed_name.to_lowercase();
+        let mut completions: Vec<(String, bool)> = read_dir
+            .filter_map(|e| e.ok())
+            .map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                (name, e.path().is_dir())
+            })
+            .filter(|(name, _)| name.to_lowercase().starts_with(&typed_name))
+            .filter(|(name, _)| !name.starts_with('.') || typed_name.starts_with('.'))
+            .collect();
+        completions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        for (name, is_dir) in completions.into_iter().take(MAX_COMPLETIONS) {
+            let full = dir.join(&name);
+            if full != path {
+                items.push(self.completion(&name, full, is_dir, typed_dir));
+            }
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_target_recognises_urls_and_domains() {
+        let https = |s: &str| Some(format!("https://{}", s));
+        assert_eq!(
+            uri_target("https://example.org/a?b=c"),
+            Some("https://example.org/a?b=c".to_string())
+        );
+        assert_eq!(
+            uri_target(" mailto:me@example.org "),
+            Some("mailto:me@example.org".to_string())
+        );
+        assert_eq!(uri_target("example.com"), https("example.com"));
+        assert_eq!(uri_target("www.example.xyz"), https("www.example.xyz"));
+        assert_eq!(uri_target("example.xyz/path"), https("example.xyz/path"));
+        assert_eq!(
+            uri_target("localhost.dev:8080"),
+            https("localhost.dev:8080")
+        );
+    }
+
+    #[test]
+    fn uri_target_leaves_file_names_and_text_alone() {
+        assert_eq!(uri_target("notes.txt"), None);
+        assert_eq!(uri_target("main.rs"), None);
+        assert_eq!(uri_target("two words.com"), None);
+        assert_eq!(uri_target(""), None);
+    }
+
+    #[test]
+    fn uri_target_opens_existing_paths() {
+        let dir = std::env::temp_dir();
+        let uri = gio::File::for_path(&dir).uri().to_string();
+        assert_eq!(uri_target(&dir.to_string_lossy()), Some(uri));
+        assert_eq!(uri_target("/no/such/void-launcher/path"), None);
+    }
+}
@@ -0,0 +1,567 @@
+use crate::matcher::{fold, parse_query, score_query};
+use crate::provider::{Item, PendingSearch, Provider, clear_grid, render_items};
+use crate::utils::{ConfFile, get_conf_map, get_conf_option};
+use dirs::home_dir;
+use eyre::{Result, eyre};
+use gtk4::{
+    self as gtk, ApplicationWindow, Entry, FlowBox,
+    gio::{self, Icon, ThemedIcon},
+    glib::{self, Variant, variant::ToVariant},
+    prelude::*,
+};
+use std::cell::RefCell;
+use std::env;
+use std::process::Command;
+use std::rc::Rc;
+
+const LOGIN1: &str = "org.freedesktop.login1";
+const LOGIN1_PATH: &str = "/org/freedesktop/login1";
+// logind resolves "auto" to the session of the caller.
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+// Screen lockers tried when "power-commands" has no "lock", logind's Lock only
+// asks a locker to lock and does nothing without one.
+const LOCKERS: [&[&str]; 4] = [
+    &["hyprlock"],
+    &["swaylock", "-f"],
+    &["gtklock"],
+    &["waylock"],
+];
+// One letter would match nearly every action.
+const MIN_QUERY: usize = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PowerAction {
+    Lock,
+    Suspend,
+    Hibernate,
+    Logout,
+    Reboot,
+    PowerOff,
+}
+
+const ACTIONS: [PowerAction; 6] = [
+    PowerAction::Lock,
+    PowerAction::Suspend,
+    PowerAction::Hibernate,
+    PowerAction::Logout,
+    PowerAction::Reboot,
+    PowerAction::PowerOff,
+];
+
+impl PowerAction {
+    fn name(self) -> &'static str {
+        match self {
+            PowerAction::Lock => "Lock",
+            PowerAction::Suspend => "Suspend",
+            PowerAction::Hibernate => "Hibernate",
+            PowerAction::Logout => "Log Out",
+            PowerAction::Reboot => "Reboot",
+            PowerAction::PowerOff => "Power Off",
+        }
+    }
+
+    /// The key under "power-commands".
+    fn key(self) -> &'static str {
+        match self {
+            PowerAction::Lock => "lock",
+            PowerAction::Suspend => "suspend",
+            PowerAction::Hibernate => "hibernate",
+            PowerAction::Logout => "logout",
+            PowerAction::Reboot => "reboot",
+            PowerAction::PowerOff => "poweroff",
+        }
+    }
+
+    fn keywords(self) -> Vec<String> {
+        let keywords: &[&str] = match self {
+            PowerAction::Lock => &["lock screen"],
+            PowerAction::Suspend => &["sleep"],
+            PowerAction::Hibernate => &["sleep"],
+            PowerAction::Logout => &["logout", "exit", "session"],
+            PowerAction::Reboot => &["restart"],
+            PowerAction::PowerOff => &["shutdown", "poweroff", "halt"],
+        };
+        keywords.iter().map(|k| k.to_string()).collect()
+    }
+
+    fn icon(self) -> Icon {
+        let name = match self {
+            PowerAction::Lock => "system-lock-screen",
+            PowerAction::Suspend => "system-suspend",
+            PowerAction::Hibernate => "system-hibernate",
+            PowerAction::Logout => "system-log-out",
+            PowerAction::Reboot => "system-reboot",
+            PowerAction::PowerOff => "system-shutdown",
+        };
+        ThemedIcon::new(name).upcast()
+    }
+
+    /// Object path, interface, method and arguments of the login1 call, locking
+    /// needs a command.
+    fn login1_call(self) -> Option<(&'static str, &'static str, &'static str, Option<Variant>)> {
+        // The boolean lets polkit ask for a password when the action needs one.
+        let interactive = Some((true,).to_variant());
+        let manager = "org.freedesktop.login1.Manager";
+        let session = "org.freedesktop.login1.Session";
+        match self {
+            PowerAction::Lock => None,
+            PowerAction::Suspend => Some((LOGIN1_PATH, manager, "Suspend", interactive)),
+            PowerAction::Hibernate => Some((LOGIN1_PATH, manager, "Hibernate", interactive)),
+            PowerAction::Logout => Some((SESSION_PATH, session, "Terminate", None)),
+            PowerAction::Reboot => Some((LOGIN1_PATH, manager, "Reboot", interactive)),
+            PowerAction::PowerOff => Some((LOGIN1_PATH, manager, "PowerOff", interactive)),
+        }
+    }
+
+    /// Word prefixes of the name rank above those of the keywords, so "re" finds
+    /// Reboot and "sh" Power Off, while a single letter finds nothing. Terms work as
+    /// for apps, `-sleep` leaves out and `name:` or `keywords:` limits to a field.
+    fn score(self, query: &str) -> Option<i64> {
+        let terms = parse_query(query);
+        if !terms
+            .iter()
+            .any(|term| !term.negated && term.text.chars().count() >= MIN_QUERY)
+        {
+            return None;
+        }
+        let word_prefix = |text: &str, prefix: &str| {
+            let text = fold(text);
+            text.starts_with(prefix) || text.split(' ').any(|word| word.starts_with(prefix))
+        };
+        let keywords = self.keywords();
+        score_query(query, |term| {
+            let in_name = word_prefix(self.name(), &term.text);
+            let in_keywords = keywords.iter().any(|k| word_prefix(k, &term.text));
+            match term.field {
+                None | Some("name") if in_name => Some(300),
+                None | Some("keywords") if in_keywords => Some(100),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// The first of the `LOCKERS` installed.
+fn find_locker() -> Option<String> {
+    LOCKERS
+        .iter()
+        .find(|locker| glib::find_program_in_path(locker[0]).is_some())
+        .map(|locker| locker.join(" "))
+}
+
+/// "power-commands" with a detected locker filling in for a missing "lock", and
+/// "power-confirm".
+fn settings() -> (Vec<(String, String)>, bool) {
+    let conf = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+    let mut commands: Vec<(String, String)> = conf
+        .as_ref()
+        .and_then(|conf| get_conf_map(conf.read(), "power-commands"))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, command)| !command.trim().is_empty())
+        .collect();
+    let lock = PowerAction::Lock.key();
+    if !commands.iter().any(|(key, _)| key == lock) {
+        commands.extend(find_locker().map(|locker| (lock.to_string(), locker)));
+    }
+    let mut confirm = conf
+        .map(|conf| get_conf_option(conf.read(), "power-confirm"))
+        .unwrap_or_default();
+    if (confirm != "true") && (confirm != "false") {
+        println!(
+            "\"{}\" isn't a valid option for power-confirm, going with default: \"true\".",
+            confirm
+        );
+        confirm = "true".to_string();
+    }
+
+    (commands, confirm == "true")
+}
+
+/// login1 lives on the system bus, VOID_LAUNCHER_LOGIN1_BUS=session points the
+/// calls at a mock service on the session bus instead.
+fn login1_bus() -> gio::BusType {
+    match env::var("VOID_LAUNCHER_LOGIN1_BUS").as_deref() {
+        Ok("session") => gio::BusType::Session,
+        _ => gio::BusType::System,
+    }
+}
+
+fn run_action(action: PowerAction, commands: &[(String, String)]) -> Result<()> {
+    if let Some((_, command)) = commands.iter().find(|(key, _)| key == action.key()) {
+        Command::new("sh").arg("-c").arg(command).spawn()?;
+        return Ok(());
+    }
+
+    let call = action.login1_call().ok_or(eyre!(
+        "No command for \"{}\" in \"power-commands\"",
+        action.key()
+    ))?;
+    let connection = gio::bus_get_sync(login1_bus(), gio::Cancellable::NONE)?;
+    call_login1(&connection, call)
+}
+
+/// Makes a `login1_call` on the connection.
+fn call_login1(
+    connection: &gio::DBusConnection,
+    (path, interface, method, parameters): (&str, &str, &str, Option<Variant>),
+) -> Result<()> {
+    connection.call_sync(
+        Some(LOGIN1),
+        path,
+        interface,
+        method,
+        parameters.as_ref(),
+        None,
+        gio::DBusCallFlags::ALLOW_INTERACTIVE_AUTHORIZATION,
+        -1,
+        gio::Cancellable::NONE,
+    )?;
+    Ok(())
+}
+
+fn perform(action: PowerAction, commands: &[(String, String)]) {
+    run_action(action, commands).unwrap_or_else(|err| {
+        eprintln!("Failed to {}: {}", action.name().to_lowercase(), err);
+    });
+}
+
+/// Replaces the results with "Confirm" and "Cancel", cancelling brings the results back.
+fn show_confirmation(
+    flowbox: &FlowBox,
+    action: PowerAction,
+    commands: Vec<(String, String)>,
+    icon_size: i32,
+    entry: &Entry,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) {
+    clear_grid(flowbox);
+
+    let window = window.clone();
+    let app = app.clone();
+    let mut confirm = Item::new(
+        &format!("Confirm {}", action.name()),
+        action.icon(),
+        Rc::new(move || {
+            window.close();
+            perform(action, &commands);
+            app.quit();
+        }),
+    );
+    // Stays in front of "Cancel".
+    confirm.score = 1;
+
+    let entry = entry.clone();
+    let cancel = Item::new(
+        "Cancel",
+        ThemedIcon::new("process-stop").upcast(),
+        Rc::new(move || {
+            entry.emit_by_name::<()>("changed", &[]);
+            entry.grab_focus();
+        }),
+    );
+    render_items(flowbox, vec![confirm, cancel], icon_size);
+
+    if let Some(child) = flowbox.child_at_index(0) {
+        flowbox.select_child(&child);
+    }
+}
+
+/// Power and session actions, offered once something is typed.
+pub struct PowerProvider {
+    flowbox: FlowBox,
+    entry: Entry,
+    window: ApplicationWindow,
+    app: gtk::Application,
+    icon_size_memory: Rc<RefCell<i32>>,
+    pending: PendingSearch,
+    // "power-commands" and "power-confirm".
+    commands: Vec<(String, String)>,
+    confirm: bool,
+}
+
+impl PowerProvider {
+    pub fn new(
+        flowbox: &FlowBox,
+        entry: &Entry,
+        window: &ApplicationWindow,
+        app: &gtk::Application,
+        icon_size_memory: Rc<RefCell<i32>>,
+        pending: &PendingSearch,
+    ) -> Self {
+        let (commands, confirm) = settings();
+        Self {
+            flowbox: flowbox.clone(),
+            entry: entry.clone(),
+            window: window.clone(),
+            app: app.clone(),
+            icon_size_memory,
+            pending: pending.clone(),
+            commands,
+            confirm,
+        }
+    }
+}
+
+impl Provider for PowerProvider {
+    fn name(&self) -> &'static str {
+        "power"
+    }
+
+    fn title(&self) -> &'static str {
+        "Actions"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let matching: Vec<(PowerAction, i64)> = ACTIONS
+            .into_iter()
+            // Without a locker there's nothing to lock with.
+            .filter(|action| {
+                action.login1_call().is_some()
+                    || self.commands.iter().any(|(key, _)| key == action.key())
+            })
+            .filter_map(|action| Some((action, action.score(query)?)))
+            .collect();
+        if matching.is_empty() {
+            return Vec::new();
+        }
+
+        let mut items = Vec::new();
+        for (action, score) in matching {
+            let commands = self.commands.clone();
+            let flowbox = self.flowbox.clone();
+            let entry = self.entry.clone();
+            let window = self.window.clone();
+            let app = self.app.clone();
+            let icon_size_memory = self.icon_size_memory.clone();
+            let pending = self.pending.clone();
+            // Locking is harmless and easy to undo, everything else asks first.
+            let activate: Rc<dyn Fn()> = if self.confirm && action != PowerAction::Lock {
+                Rc::new(move || {
+                    // A search still on its way would draw over the confirmation.
+                    pending.stop();
+                    let icon_size = *icon_size_memory.borrow();
+                    show_confirmation(
+                        &flowbox,
+                        action,
+                        commands.clone(),
+                        icon_size,
+                        &entry,
+                        &window,
+                        &app,
+                    );
+                })
+            } else {
+                Rc::new(move || {
+                    window.close();
+                    perform(action, &commands);
+                    app.quit();
+                })
+            };
+            let mut item = Item::new(action.name(), action.icon(), activate);
+            item.score = score;
+            item.highlight(query);
+            items.push(item);
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Stdio};
+    use std::sync::mpsc;
+    use std::thread;
+
+    const MOCK_LOGIN1: &str = r#"<node>
+        <interface name="org.freedesktop.login1.Manager">
+            <method name="Suspend"><arg type="b" direction="in"/></method>
+            <method name="Hibernate"><arg type="b" direction="in"/></method>
+            <method name="Reboot"><arg type="b" direction="in"/></method>
+            <method name="PowerOff"><arg type="b" direction="in"/></method>
+        </interface>
+        <interface name="org.freedesktop.login1.Session">
+            <method name="Terminate"/>
+        </interface>
+    </node>"#;
+
+    // Path, interface, method and arguments of a call the mock received.
+    type Call = (String, String, String, Variant);
+
+    /// A message bus of the test's own, so its address doesn't go through the
+    /// environment other tests read.
+    struct TestBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl TestBus {
+        fn start() -> Self {
+            let dir = env::temp_dir().join(format!("void-launcher-bus-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let config = dir.join("bus.conf");
+            // The policy GIO's own test bus uses.
+            let policy = concat!(
+                r#"<policy context="default">"#,
+                r#"<allow send_destination="*" eavesdrop="true"/>"#,
+                r#"<allow eavesdrop="true"/><allow own="*"/>"#,
+                "</policy>"
+            );
+            let listen = format!("<listen>unix:dir={}</listen>", dir.to_string_lossy());
+            fs::write(
+                &config,
+                format!(
+                    "<busconfig><type>session</type>{}{}</busconfig>",
+                    listen, policy
+                ),
+            )
+            .unwrap();
+            let mut daemon = Command::new("dbus-daemon")
+                .arg(format!("--config-file={}", config.to_string_lossy()))
+                .args(["--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap();
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+            Self {
+                daemon,
+                address: address.trim().to_string(),
+            }
+        }
+    }
+
+    impl Drop for TestBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn connect(address: &str) -> gio::DBusConnection {
+        gio::DBusConnection::for_address_sync(
+            address,
+            gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+            None,
+            gio::Cancellable::NONE,
+        )
+        .unwrap()
+    }
+
+    /// Owns the login1 name on the bus at `address` from a thread of its own and
+    /// reports every call to `calls`.
+    fn mock_login1(address: String, calls: mpsc::Sender<Call>) {
+        let (ready, started) = mpsc::channel();
+        thread::spawn(move || {
+            let context = glib::MainContext::new();
+            context
+                .with_thread_default(|| {
+                    let connection = connect(&address);
+                    let node = gio::DBusNodeInfo::for_xml(MOCK_LOGIN1).unwrap();
+                    for (path, interface) in [
+                        (LOGIN1_PATH, "org.freedesktop.login1.Manager"),
+                        (SESSION_PATH, "org.freedesktop.login1.Session"),
+                    ] {
+                        let calls = calls.clone();
+                        connection
+                            .register_object(path, &node.lookup_interface(interface).unwrap())
+                            .method_call(move |_, _, path, interface, method, args, invocation| {
+                                let interface = interface.unwrap_or_default().to_string();
+                                calls
+                                    .send((path.to_string(), interface, method.to_string(), args))
+                                    .unwrap();
+                                invocation.return_value(None);
+                            })
+                            .build()
+                            .unwrap();
+                    }
+                    connection
+                        .call_sync(
+                            Some("org.freedesktop.DBus"),
+                            "/org/freedesktop/DBus",
+                            "org.freedesktop.DBus",
+                            "RequestName",
+                            Some(&(LOGIN1, 0u32).to_variant()),
+                            None,
+                            gio::DBusCallFlags::NONE,
+                            -1,
+                            gio::Cancellable::NONE,
+                        )
+                        .unwrap();
+                    ready.send(()).unwrap();
+                    loop {
+                        context.iteration(true);
+                    }
+                })
+                .unwrap();
+        });
+        started.recv().unwrap();
+    }
+
+    #[test]
+    fn actions_match_word_prefixes_of_name_and_keywords() {
+        let score = |query| {
+            ACTIONS
+                .into_iter()
+                .filter_map(|action| Some((action.name(), action.score(query)?)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(score("re"), [("Reboot", 300)]);
+        assert_eq!(score("sh"), [("Power Off", 100)]);
+        assert_eq!(score("power off"), [("Power Off", 300)]);
+        assert_eq!(score("SLEEP"), [("Suspend", 100), ("Hibernate", 100)]);
+        assert_eq!(score("sleep -hib"), [("Suspend", 100)]);
+        assert_eq!(score("keywords:restart"), [("Reboot", 100)]);
+        assert!(score("name:restart").is_empty());
+        assert!(score("r").is_empty());
+        assert!(score("-lock").is_empty());
+        assert!(score("reboot now").is_empty());
+    }
+
+    #[test]
+    fn login1_calls_reach_the_service() {
+        let bus = TestBus::start();
+        let (sender, calls) = mpsc::channel();
+        mock_login1(bus.address.clone(), sender);
+        let connection = connect(&bus.address);
+        let call = |action: PowerAction| {
+            call_login1(&connection, action.login1_call().unwrap()).unwrap();
+            calls.recv().unwrap()
+        };
+
+        let (path, interface, method, args) = call(PowerAction::Suspend);
+        assert_eq!(
+            (path.as_str(), interface.as_str(), method.as_str()),
+            (LOGIN1_PATH, "org.freedesktop.login1.Manager", "Suspend")
+        );
+        assert_eq!(args, (true,).to_variant());
+
+        let (_, _, method, args) = call(PowerAction::Reboot);
+        assert_eq!((method.as_str(), args), ("Reboot", (true,).to_variant()));
+
+        let (path, interface, method, args) = call(PowerAction::Logout);
+        assert_eq!(
+            (path.as_str(), interface.as_str(), method.as_str()),
+            (SESSION_PATH, "org.freedesktop.login1.Session", "Terminate")
+        );
+        assert_eq!(args, ().to_variant());
+    }
+
+    #[test]
+    fn locking_needs_a_command() {
+        assert!(PowerAction::Lock.login1_call().is_none());
+        assert!(run_action(PowerAction::Lock, &[]).is_err());
+    }
+}
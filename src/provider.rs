@@ -0,0 +1,731 @@
+use crate::apps::{AppsProvider, CategoryApps, InstalledApps};
+use crate::categories::{CategoriesProvider, category_query, completing_categories, find_category};
+use crate::clipboard::{ClipboardProvider, clipboard_query};
+use crate::custom::CustomProvider;
+use crate::emoji::{SymbolSet, SymbolsProvider, symbol_query};
+use crate::files::{FilesProvider, files_query};
+use crate::matcher::match_positions;
+use crate::menu::{MenuItems, attach_context_menu};
+use crate::open::{OpenProvider, PathProvider, path_query};
+use crate::power::PowerProvider;
+use crate::recent::{RecentProvider, recent_query};
+use crate::script::script_query;
+use crate::ssh::{SshProvider, ssh_query};
+use crate::ui::get_object;
+use crate::utils::{ConfFile, get_conf_map, get_conf_option};
+use crate::websearch::{SearchEngines, WebSearch, WebSearchProvider};
+use dirs::home_dir;
+use eyre::Result;
+use gtk4::{
+    self as gtk, ApplicationWindow, Box, Builder, Button, Entry, FlowBox, Image, Label,
+    Orientation,
+    gio::{self, AppInfo, Icon},
+    glib,
+    prelude::*,
+};
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+pub type ItemsFuture = Pin<std::boxed::Box<dyn Future<Output = Vec<Item>>>>;
+
+/// A single result in the grid, whatever source it came from.
+#[derive(Clone)]
+pub struct Item {
+    pub title: String,
+    // Shown as the tooltip.
+    pub subtitle: Option<String>,
+    pub icon: Icon,
+    // Higher ranks first, equal scores keep the provider's order.
+    pub score: i64,
+    pub activate: Rc<dyn Fn()>,
+    // Builds the context menu, called when the menu opens.
+    pub actions: Option<Rc<dyn Fn() -> MenuItems>>,
+    // Lets Shift+Enter start an app on another workspace, looked up when it's pressed.
+    pub app_info: Option<Rc<dyn Fn() -> Option<AppInfo>>>,
+    // Char positions of the title the query matched, shown highlighted.
+    pub matched: Vec<usize>,
+    // A short note under the title, like which install of an app this is.
+    pub badge: Option<String>,
+    // Drawn instead of the icon, for emoji and other symbols.
+    pub glyph: Option<String>,
+    // Drawn at twice the icon size, for image previews.
+    pub large_icon: bool,
+}
+
+impl Item {
+    pub fn new(title: &str, icon: Icon, activate: Rc<dyn Fn()>) -> Self {
+        Self {
+            title: title.to_string(),
+            subtitle: None,
+            icon,
+            score: 0,
+            activate,
+            actions: None,
+            app_info: None,
+            matched: Vec::new(),
+            badge: None,
+            glyph: None,
+            large_icon: false,
+        }
+    }
+
+    /// Highlights the part of the title that `query` matched.
+    pub fn highlight(&mut self, query: &str) {
+        self.matched = match_positions(query, &self.title);
+    }
+}
+
+/// A source of results for the search bar.
+pub trait Provider {
+    /// Key of the provider in "provider-weights" and "provider-max-results".
+    fn name(&self) -> &'static str;
+    /// Header of the provider's section.
+    fn title(&self) -> &'static str;
+    fn query(&self, query: &str) -> Vec<Item>;
+    /// The query as the search bar runs it, by default just `query`. Slow providers
+    /// do their work on the gio thread pool and give up once `cancellable` is cancelled.
+    fn query_async(&self, query: &str, _cancellable: &gio::Cancellable) -> ItemsFuture {
+        let items = self.query(query);
+        std::boxed::Box::pin(async move { items })
+    }
+    /// Close matches offered under "Did you mean" when no provider found anything.
+    fn suggest(&self, _query: &str) -> Vec<Item> {
+        Vec::new()
+    }
+}
+
+/// The results of one provider, ranked and capped.
+#[derive(Clone)]
+pub struct Section {
+    pub title: &'static str,
+    pub weight: i64,
+    pub items: Vec<Item>,
+}
+
+/// Every source of results, built once with the window.
+pub struct Providers {
+    // URLs and paths, web search keywords, desktop apps, custom entries and power
+    // actions, the sources behind the plain search.
+    plain: Vec<Rc<dyn Provider>>,
+    // Sources with a prefix of their own blended in after them.
+    blended: Vec<Rc<dyn Provider>>,
+    emoji: Rc<dyn Provider>,
+    unicode: Rc<dyn Provider>,
+    clipboard: Rc<dyn Provider>,
+    recent: Rc<dyn Provider>,
+    ssh: Rc<dyn Provider>,
+    categories: Rc<dyn Provider>,
+    path: Rc<dyn Provider>,
+    files: Rc<dyn Provider>,
+    // Also in `plain`, searched within a category for `cat:` queries.
+    apps: Rc<AppsProvider>,
+    layout: Rc<ResultsLayout>,
+    engines: Rc<SearchEngines>,
+    pending: PendingSearch,
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+/// Where the search bar text goes: the providers, the query they get, how their
+/// results are laid out and the "Search the web for …" offered when they find
+/// nothing.
+pub struct Route {
+    pub providers: Vec<Rc<dyn Provider>>,
+    // How many of the first providers decide whether the search found anything,
+    // the blended sources after them don't hold back "Did you mean" and the fallback.
+    pub primary: usize,
+    pub query: String,
+    pub layout: Rc<ResultsLayout>,
+    pub fallback: Option<WebSearch>,
+}
+
+// Shortest query the prefixed sources answer in the plain search.
+const MIN_BLENDED_QUERY: usize = 3;
+
+/// A source with a prefix of its own, like files or SSH hosts, taking part in the
+/// plain search once the query is long enough to mean something.
+struct InPlainSearch(Rc<dyn Provider>);
+
+impl Provider for InPlainSearch {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn title(&self) -> &'static str {
+        self.0.title()
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        if query.trim().chars().count() < MIN_BLENDED_QUERY {
+            return Vec::new();
+        }
+        self.0.query(query)
+    }
+
+    fn query_async(&self, query: &str, cancellable: &gio::Cancellable) -> ItemsFuture {
+        if query.trim().chars().count() < MIN_BLENDED_QUERY {
+            return std::boxed::Box::pin(async { Vec::new() });
+        }
+        self.0.query_async(query, cancellable)
+    }
+}
+
+/// "results-layout", "provider-weights" and "provider-max-results", read once.
+/// The default has no weights and no caps, for sources shown on their own.
+#[derive(Default)]
+pub struct ResultsLayout {
+    blended: bool,
+    weights: Vec<(String, i64)>,
+    max_results: Vec<(String, i64)>,
+}
+
+impl ResultsLayout {
+    fn load() -> Self {
+        let conf = home_dir()
+            .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+        let mut layout = conf
+            .as_ref()
+            .map(|conf| get_conf_option(conf.read(), "results-layout"))
+            .unwrap_or_default();
+        if (layout != "sections") && (layout != "blended") {
+            println!(
+                "\"{}\" isn't a valid value for \"results-layout\", going with default: \"sections\".",
+                layout
+            );
+            layout = "sections".to_string();
+        }
+        let settings = |which: &str| {
+            conf.as_ref()
+                .and_then(|conf| get_conf_map(conf.read(), which))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, value)| {
+                    let value = value.parse().unwrap_or_else(|_| {
+                        println!(
+                            "\"{}\" isn't a valid value for \"{}\" of \"{}\", going with \"0\".",
+                            value, name, which
+                        );
+                        0
+                    });
+                    (name, value)
+                })
+                .collect()
+        };
+        Self {
+            blended: layout == "blended",
+            weights: settings("provider-weights"),
+            max_results: settings("provider-max-results"),
+        }
+    }
+
+    fn setting(settings: &[(String, i64)], name: &str) -> i64 {
+        settings
+            .iter()
+            .find(|(key, _)| key == name)
+            .map_or(0, |(_, value)| *value)
+    }
+
+    /// Ranks the provider's items and cuts them at its "provider-max-results".
+    fn make_section(&self, provider: &dyn Provider, mut items: Vec<Item>) -> Section {
+        items.sort_by_key(|item| std::cmp::Reverse(item.score));
+        let max = Self::setting(&self.max_results, provider.name());
+        if max > 0 {
+            items.truncate(max as usize);
+        }
+        Section {
+            title: provider.title(),
+            weight: Self::setting(&self.weights, provider.name()),
+            items,
+        }
+    }
+}
+
+impl Providers {
+    pub fn new(
+        builder: &Builder,
+        app: &gtk::Application,
+        icon_size_memory: Rc<RefCell<i32>>,
+        installed: &InstalledApps,
+    ) -> Result<Self> {
+        let window: ApplicationWindow = get_object(builder, "window")?;
+        let flowbox: FlowBox = get_object(builder, "apps-box")?;
+        let entry: Entry = get_object(builder, "search-entry")?;
+        let files: Rc<dyn Provider> = Rc::new(FilesProvider::new(&window, app));
+        let clipboard: Rc<dyn Provider> = Rc::new(ClipboardProvider::new(&window, app));
+        let recent: Rc<dyn Provider> = Rc::new(RecentProvider::new(installed, &window, app));
+        let ssh: Rc<dyn Provider> = Rc::new(SshProvider::new(&window, app));
+        let engines = Rc::new(SearchEngines::load());
+        let apps = Rc::new(AppsProvider::new(installed, &window, app));
+        let pending = PendingSearch::default();
+        Ok(Self {
+            plain: vec![
+                Rc::new(OpenProvider::new(&window, app)),
+                Rc::new(WebSearchProvider::new(engines.clone(), &window, app)),
+                apps.clone(),
+                Rc::new(CustomProvider::new(&window, app)),
+                Rc::new(PowerProvider::new(
+                    &flowbox,
+                    &entry,
+                    &window,
+                    app,
+                    icon_size_memory,
+                    &pending,
+                )),
+            ],
+            blended: vec![
+                Rc::new(InPlainSearch(files.clone())),
+                Rc::new(InPlainSearch(recent.clone())),
+                Rc::new(InPlainSearch(ssh.clone())),
+                Rc::new(InPlainSearch(clipboard.clone())),
+            ],
+            emoji: Rc::new(SymbolsProvider::emoji(&window, app)),
+            unicode: Rc::new(SymbolsProvider::unicode(&window, app)),
+            clipboard,
+            recent,
+            ssh,
+            categories: Rc::new(CategoriesProvider::new(&entry)),
+            path: Rc::new(PathProvider::new(&entry, &window, app)),
+            files,
+            apps,
+            layout: Rc::new(ResultsLayout::load()),
+            engines,
+            pending,
+            window,
+            app: app.clone(),
+        })
+    }
+
+    pub fn apps(&self) -> &Rc<AppsProvider> {
+        &self.apps
+    }
+
+    pub fn pending(&self) -> &PendingSearch {
+        &self.pending
+    }
+
+    /// Picks the providers by the prefix of `text`, like `:` for emoji or `ssh ` for
+    /// SSH hosts. With a `mode` other than "apps" the text goes to that mode.
+    pub fn route(&self, text: &str, mode: &str) -> Route {
+        let only = |provider: &Rc<dyn Provider>, query: &str| Route {
+            providers: vec![provider.clone()],
+            primary: 1,
+            query: query.to_string(),
+            layout: Rc::new(ResultsLayout::default()),
+            fallback: None,
+        };
+        if let Some((set, query)) = symbol_query(text, mode) {
+            let provider = match set {
+                SymbolSet::Emoji => &self.emoji,
+                SymbolSet::Unicode => &self.unicode,
+            };
+            return only(provider, query);
+        }
+        if let Some(query) = clipboard_query(text, mode) {
+            return only(&self.clipboard, query);
+        }
+        if let Some(query) = recent_query(text, mode) {
+            return only(&self.recent, query);
+        }
+        if let Some(query) = ssh_query(text) {
+            return only(&self.ssh, query);
+        }
+        // The start of a bar category lists the ones it may become, other categories are
+        // left to the `cat:` filter of the plain search.
+        if let Some((name, _)) = category_query(text).filter(|(n, query)| {
+            query.is_empty()
+                && find_category(n).is_none()
+                && completing_categories(n).next().is_some()
+        }) {
+            return only(&self.categories, name);
+        }
+        if path_query(text).is_some() {
+            return only(&self.path, text);
+        }
+        if let Some((provider, query)) = script_query(text, &self.window, &self.app) {
+            let provider: Rc<dyn Provider> = Rc::new(provider);
+            return only(&provider, query);
+        }
+        if let Some((category, query)) =
+            category_query(text).and_then(|(n, query)| Some((find_category(n)?, query)))
+        {
+            let provider: Rc<dyn Provider> =
+                Rc::new(CategoryApps::new(self.apps.clone(), category));
+            return only(&provider, query);
+        }
+        if let Some(query) = files_query(text) {
+            return only(&self.files, query);
+        }
+        Route {
+            providers: self.plain.iter().chain(&self.blended).cloned().collect(),
+            primary: self.plain.len(),
+            query: text.to_string(),
+            layout: self.layout.clone(),
+            fallback: self.engines.fallback_search(text),
+        }
+    }
+}
+
+/// Asks every provider right away, for the grid shown at startup. `apps` stand in for
+/// what the apps provider would answer, like its `startup_items`.
+pub fn query_all(route: &Route, mut apps: Option<Vec<Item>>) -> Vec<Section> {
+    route
+        .providers
+        .iter()
+        .map(|provider| {
+            let items = match apps.take_if(|_| provider.name() == "apps") {
+                Some(items) => items,
+                None => provider.query(&route.query),
+            };
+            route.layout.make_section(provider.as_ref(), items)
+        })
+        .filter(|section| !section.items.is_empty())
+        .collect()
+}
+
+// How many "Did you mean" results are shown at most.
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Renders the providers' close matches under a "Did you mean" header.
+fn render_suggestions(
+    flowbox: &FlowBox,
+    providers: &[Rc<dyn Provider>],
+    query: &str,
+    icon_size: i32,
+) {
+    let mut items: Vec<Item> = providers
+        .iter()
+        .flat_map(|provider| provider.suggest(query))
+        .collect();
+    if items.is_empty() {
+        return;
+    }
+    items.sort_by_key(|item| std::cmp::Reverse(item.score));
+    items.truncate(MAX_SUGGESTIONS);
+    let columns = flowbox.max_children_per_line().max(1) as i32;
+    add_header(flowbox, "Did you mean", columns);
+    render_items(flowbox, items, icon_size);
+    pad_row(flowbox, columns, child_count(flowbox));
+}
+
+/// The search still running and the one waiting out the debounce, stopped before
+/// anything else takes over the grid.
+#[derive(Clone, Default)]
+pub struct PendingSearch {
+    running: Rc<RefCell<Option<gio::Cancellable>>>,
+    scheduled: Rc<RefCell<Option<glib::SourceId>>>,
+}
+
+impl PendingSearch {
+    pub fn stop(&self) {
+        if let Some(cancellable) = self.running.borrow_mut().take() {
+            cancellable.cancel();
+        }
+        if let Some(source) = self.scheduled.borrow_mut().take() {
+            source.remove();
+        }
+    }
+
+    /// Stops the previous search and runs `search` once `delay` has passed.
+    pub fn schedule<F>(&self, delay: std::time::Duration, search: F)
+    where
+        F: FnOnce(&gio::Cancellable) + 'static,
+    {
+        self.stop();
+        let cancellable = gio::Cancellable::new();
+        *self.running.borrow_mut() = Some(cancellable.clone());
+        let scheduled = self.scheduled.clone();
+        let source = glib::timeout_add_local_once(delay, move || {
+            scheduled.borrow_mut().take();
+            search(&cancellable);
+        });
+        *self.scheduled.borrow_mut() = Some(source);
+    }
+}
+
+/// Runs the providers for one query, the grid is rebuilt as their results come in.
+/// Old results stay up until the first answer. When none of the primary providers
+/// found anything, their close matches are offered after the rest and `on_empty` runs.
+pub fn run_search<F>(
+    flowbox: &FlowBox,
+    route: Route,
+    icon_size: i32,
+    cancellable: &gio::Cancellable,
+    on_empty: F,
+) where
+    F: Fn() + 'static,
+{
+    let Route {
+        providers,
+        primary,
+        query,
+        layout,
+        ..
+    } = route;
+    let collected: Rc<RefCell<Vec<(usize, Section)>>> = Rc::new(RefCell::new(Vec::new()));
+    let pending = Rc::new(Cell::new(providers.len()));
+    let render_scheduled = Rc::new(Cell::new(false));
+    let on_empty = Rc::new(on_empty);
+    let primary_providers: Rc<[Rc<dyn Provider>]> = providers[..primary].into();
+
+    for (index, provider) in providers.into_iter().enumerate() {
+        let future = provider.query_async(&query, cancellable);
+        let flowbox = flowbox.clone();
+        let cancellable = cancellable.clone();
+        let collected = collected.clone();
+        let pending = pending.clone();
+        let render_scheduled = render_scheduled.clone();
+        let on_empty = on_empty.clone();
+        let primary_providers = primary_providers.clone();
+        let query = query.clone();
+        let layout = layout.clone();
+        glib::spawn_future_local(async move {
+            let items = future.await;
+            if cancellable.is_cancelled() {
+                return;
+            }
+            pending.set(pending.get() - 1);
+            let section = layout.make_section(provider.as_ref(), items);
+            if !section.items.is_empty() {
+                collected.borrow_mut().push((index, section));
+            }
+            if render_scheduled.replace(true) {
+                return;
+            }
+            // Providers answering within the same main loop iteration share one render.
+            glib::idle_add_local_once(move || {
+                render_scheduled.set(false);
+                if cancellable.is_cancelled() {
+                    return;
+                }
+                let mut sections = collected.borrow().clone();
+                let done = pending.get() == 0;
+                if sections.is_empty() && !done {
+                    return;
+                }
+                let found = sections.iter().any(|(index, _)| *index < primary);
+                sections.sort_by_key(|(index, _)| *index);
+                clear_grid(&flowbox);
+                render_sections(
+                    &flowbox,
+                    sections.into_iter().map(|(_, section)| section).collect(),
+                    &layout,
+                    icon_size,
+                );
+                if done && !found {
+                    render_suggestions(&flowbox, &primary_providers, &query, icon_size);
+                    on_empty();
+                }
+            });
+        });
+    }
+}
+
+pub fn clear_grid(flowbox: &FlowBox) {
+    while let Some(child) = flowbox.first_child() {
+        flowbox.remove(&child);
+    }
+}
+
+/// The text as Pango markup with the chars at `matched` wrapped in a span colored
+/// like the `match-highlight` CSS class.
+fn highlight_markup(text: &str, matched: &[usize], color: &str) -> String {
+    let mut markup = String::new();
+    let mut open = false;
+    for (index, c) in text.chars().enumerate() {
+        let highlighted = matched.contains(&index);
+        if highlighted && !open {
+            markup.push_str(&format!("<span weight=\"bold\" foreground=\"{}\">", color));
+        } else if !highlighted && open {
+            markup.push_str("</span>");
+        }
+        open = highlighted;
+        markup.push_str(&glib::markup_escape_text(&c.to_string()));
+    }
+    if open {
+        markup.push_str("</span>");
+    }
+    markup
+}
+
+/// The color themes give to `.match-highlight`, Pango spans can't be styled by CSS directly.
+fn highlight_color() -> String {
+    let probe = Label::new(None);
+    probe.add_css_class("match-highlight");
+    let color = probe.color();
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        (color.red() * 255.0).round() as u8,
+        (color.green() * 255.0).round() as u8,
+        (color.blue() * 255.0).round() as u8
+    )
+}
+
+/// The button of a result, `highlight` being the `highlight_color`. Returns its image
+/// too, unless it shows a glyph or a preview.
+fn result_widgets(item: &Item, icon_size: i32, highlight: &str) -> (Button, Option<Image>) {
+    let result_box = Box::new(Orientation::Vertical, 5);
+    let image = match &item.glyph {
+        Some(glyph) => {
+            let glyph = Label::new(Some(glyph));
+            glyph.add_css_class("symbol-glyph");
+            result_box.append(&glyph);
+            None
+        }
+        None => {
+            let image = Image::from_gicon(&item.icon);
+            let size = if item.large_icon {
+                icon_size * 2
+            } else {
+                icon_size
+            };
+            image.set_pixel_size(size);
+            image.set_valign(gtk4::Align::Center);
+            image.set_halign(gtk4::Align::Center);
+            result_box.append(&image);
+            Some(image).filter(|_| !item.large_icon)
+        }
+    };
+
+    let text = &item.title;
+    let label = Label::new(Some(text));
+    if !item.matched.is_empty() {
+        label.set_markup(&highlight_markup(text, &item.matched, highlight));
+    }
+    label.set_justify(gtk::Justification::Fill);
+    label.add_css_class("app-label");
+    label.set_ellipsize(pango::EllipsizeMode::End);
+    label.set_max_width_chars(5);
+    label.set_wrap(false);
+
+    result_box.append(&label);
+    result_box.add_css_class("appynka");
+    let button = Button::builder().child(&result_box).build();
+    button.set_tooltip_text(Some(item.subtitle.as_deref().unwrap_or(text)));
+    button.add_css_class("flat");
+    button.add_css_class("app-button");
+    (button, image)
+}
+
+fn add_badge(button: &Button, badge: &str) {
+    let Some(result_box) = button.child().and_downcast::<Box>() else {
+        return;
+    };
+    let label = Label::new(Some(badge));
+    label.add_css_class("app-badge");
+    label.set_ellipsize(pango::EllipsizeMode::End);
+    label.set_max_width_chars(5);
+    result_box.append(&label);
+}
+
+fn child_count(flowbox: &FlowBox) -> i32 {
+    let mut count = 0;
+    while flowbox.child_at_index(count).is_some() {
+        count += 1;
+    }
+    count
+}
+
+/// Fills the rest of the last row with empty cells, so the next section starts on a new
+/// line. The cells from `from` on are insensitive, so keyboard navigation skips them.
+fn pad_row(flowbox: &FlowBox, columns: i32, from: i32) {
+    let count = child_count(flowbox);
+    if count % columns != 0 {
+        for _ in count % columns..columns {
+            flowbox.insert(&Label::new(None), -1);
+        }
+    }
+    for index in from..child_count(flowbox) {
+        if let Some(child) = flowbox.child_at_index(index) {
+            child.set_sensitive(false);
+            child.set_can_focus(false);
+            child.add_css_class("section-filler");
+        }
+    }
+}
+
+/// A header taking a whole row of the grid.
+fn add_header(flowbox: &FlowBox, title: &str, columns: i32) {
+    let start = child_count(flowbox);
+    let label = Label::new(Some(title));
+    label.set_halign(gtk4::Align::Start);
+    label.set_ellipsize(pango::EllipsizeMode::End);
+    label.add_css_class("section-header");
+    flowbox.insert(&label, -1);
+    pad_row(flowbox, columns, start);
+}
+
+/// Renders the sections under their headers, or as a single list ranked by
+/// score plus weight with "results-layout": "blended". A lone section gets no header.
+pub fn render_sections(
+    flowbox: &FlowBox,
+    mut sections: Vec<Section>,
+    layout: &ResultsLayout,
+    icon_size: i32,
+) -> Vec<Image> {
+    if layout.blended || sections.len() < 2 {
+        let items = sections
+            .into_iter()
+            .flat_map(|section| {
+                let weight = section.weight;
+                section.items.into_iter().map(move |mut item| {
+                    item.score += weight;
+                    item
+                })
+            })
+            .collect();
+        return render_items(flowbox, items, icon_size);
+    }
+
+    // Stable, so equal weights keep the provider order.
+    sections.sort_by_key(|section| std::cmp::Reverse(section.weight));
+    let columns = flowbox.max_children_per_line().max(1) as i32;
+    let mut images = Vec::new();
+    for section in sections {
+        add_header(flowbox, section.title, columns);
+        images.extend(render_items(flowbox, section.items, icon_size));
+        pad_row(flowbox, columns, child_count(flowbox));
+    }
+    images
+}
+
+/// Adds the items to the grid, best score first. Returns the images so the
+/// startup grid can resize them once the window size is known.
+pub fn render_items(flowbox: &FlowBox, mut items: Vec<Item>, icon_size: i32) -> Vec<Image> {
+    items.sort_by_key(|item| std::cmp::Reverse(item.score));
+    let highlight = items
+        .iter()
+        .any(|item| !item.matched.is_empty())
+        .then(highlight_color)
+        .unwrap_or_default();
+    let mut images = Vec::new();
+    for item in items {
+        let (button, image) = result_widgets(&item, icon_size, &highlight);
+        images.extend(image);
+        if let Some(badge) = &item.badge {
+            add_badge(&button, badge);
+        }
+
+        let flowbox_clone = flowbox.clone();
+        let button_clone = button.clone();
+        let activate = item.activate.clone();
+        button.connect_clicked(move |_| {
+            flowbox_clone.unselect_all();
+            button_clone.add_css_class("selected-button");
+            activate();
+        });
+        unsafe {
+            button.set_data("activate", item.activate);
+            if let Some(app_info) = item.app_info {
+                button.set_data("app-info", app_info);
+            }
+        }
+        if let Some(actions) = item.actions {
+            attach_context_menu(&button, move || actions());
+        }
+
+        flowbox.insert(&button, -1);
+    }
+    images
+}
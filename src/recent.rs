@@ -0,0 +1,231 @@
+use crate::apps::InstalledApps;
+use crate::events::launch_app;
+use crate::matcher::matches;
+use crate::provider::{Item, Provider};
+use gtk4::{
+    self as gtk, ApplicationWindow, RecentInfo, RecentManager,
+    gio::{self, AppInfo, AppLaunchContext, DesktopAppInfo},
+    prelude::*,
+};
+use std::path::Path;
+use std::rc::Rc;
+
+const MAX_RESULTS: usize = 100;
+const MAX_PER_APP: usize = 10;
+
+/// Splits the "recent " prefix off the query, with `--mode recent` no prefix is needed.
+pub fn recent_query<'a>(text: &'a str, mode: &str) -> Option<&'a str> {
+    if let Some(rest) = text.strip_prefix("recent ") {
+        Some(rest)
+    } else if mode == "recent" {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+/// Entries of `~/.local/share/recently-used.xbel` that still exist, newest first.
+pub fn load_recent() -> Vec<RecentInfo> {
+    let mut items: Vec<RecentInfo> = RecentManager::default()
+        .items()
+        .into_iter()
+        .filter(|info| !info.is_local() || info.exists())
+        .collect();
+    items.sort_by_key(|info| std::cmp::Reverse(info.modified().to_unix()));
+    items
+}
+
+/// The program of an exec line recorded with a file, like "gimp" for "'gimp %u'".
+fn exec_binary(exec: &str) -> Option<String> {
+    let binary = exec
+        .trim_matches('\'')
+        .split_whitespace()
+        .next()?
+        .trim_matches('\'');
+    Path::new(binary)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+fn recorded_exec(info: &RecentInfo, app_name: &str) -> Option<String> {
+    info.application_info(app_name)
+        .map(|(exec, _, _)| exec.to_string())
+}
+
+/// Apps record files under their desktop id or display name, the exec line they
+/// recorded settles the cases where neither matches.
+fn recorded_by<F>(appynka: &AppInfo, app_name: &str, exec: F) -> bool
+where
+    F: FnOnce() -> Option<String>,
+{
+    let id = appynka
+        .id()
+        .map(|id| id.trim_end_matches(".desktop").to_string());
+    let binary = appynka
+        .executable()
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string());
+    id.as_deref() == Some(app_name)
+        || appynka.name() == app_name
+        || (binary.is_some() && exec().as_deref().and_then(exec_binary) == binary)
+}
+
+pub fn files_for_app(appynka: &AppInfo) -> Vec<RecentInfo> {
+    load_recent()
+        .into_iter()
+        .filter(|info| {
+            info.applications()
+                .iter()
+                .any(|app_name| recorded_by(appynka, app_name, || recorded_exec(info, app_name)))
+        })
+        .take(MAX_PER_APP)
+        .collect()
+}
+
+/// The installed app that last opened the file, looked up among `apps`.
+fn owner_app(info: &RecentInfo, apps: &[AppInfo]) -> Option<AppInfo> {
+    let app_name = info.last_application();
+    if let Some(desktop_info) = DesktopAppInfo::new(&format!("{}.desktop", app_name)) {
+        return Some(desktop_info.upcast());
+    }
+    apps.iter()
+        .find(|appynka| recorded_by(appynka, &app_name, || recorded_exec(info, &app_name)))
+        .cloned()
+        .or_else(|| info.create_app_info(Some(&app_name)).ok().flatten())
+}
+
+fn open_recent(info: &RecentInfo, owner: Option<AppInfo>) {
+    let uri = info.uri();
+    match owner {
+        Some(appynka) => launch_app(&appynka, &[&uri], None),
+        None => {
+            let context = AppLaunchContext::new();
+            AppInfo::launch_default_for_uri(&uri, Some(&context)).unwrap_or_else(|err| {
+                eprintln!("Failed to open {}: {}", uri, err);
+            });
+        }
+    }
+}
+
+/// Files from `~/.local/share/recently-used.xbel`, each opened with the app that last used it.
+pub struct RecentProvider {
+    installed: InstalledApps,
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl RecentProvider {
+    pub fn new(
+        installed: &InstalledApps,
+        window: &ApplicationWindow,
+        app: &gtk::Application,
+    ) -> Self {
+        Self {
+            installed: installed.clone(),
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+}
+
+impl Provider for RecentProvider {
+    fn name(&self) -> &'static str {
+        "recent"
+    }
+
+    fn title(&self) -> &'static str {
+        "Recent files"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let query = query.trim();
+        let apps: Vec<AppInfo> = self
+            .installed
+            .all()
+            .iter()
+            .filter(|appynka| appynka.should_show())
+            .cloned()
+            .collect();
+        load_recent()
+            .into_iter()
+            .filter(|info| matches(query, &info.display_name(), &[info.uri().to_string()]))
+            .take(MAX_RESULTS)
+            .map(|info| {
+                let icon = info
+                    .gicon()
+                    .unwrap_or_else(|| gio::content_type_get_icon(&info.mime_type()));
+                let title = info.display_name().to_string();
+                let subtitle = info.uri_display().map(|uri| uri.to_string());
+                let owner = owner_app(&info, &apps);
+                let window = self.window.clone();
+                let app = self.app.clone();
+                let mut item = Item::new(
+                    &title,
+                    icon,
+                    Rc::new(move || {
+                        window.close();
+                        open_recent(&info, owner.clone());
+                        app.quit();
+                    }),
+                );
+                item.subtitle = subtitle;
+                item.highlight(query);
+                item
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::tests::desktop_app;
+
+    #[test]
+    fn recent_query_needs_the_prefix_or_mode() {
+        assert_eq!(recent_query("recent notes", "apps"), Some("notes"));
+        assert_eq!(recent_query("notes", "recent"), Some("notes"));
+        assert_eq!(recent_query("notes", "apps"), None);
+    }
+
+    #[test]
+    fn exec_lines_give_their_program() {
+        assert_eq!(
+            exec_binary("'gnome-text-editor %U'").as_deref(),
+            Some("gnome-text-editor")
+        );
+        assert_eq!(exec_binary("'/usr/bin/gimp' %u").as_deref(), Some("gimp"));
+        assert_eq!(exec_binary("krita").as_deref(), Some("krita"));
+        assert_eq!(exec_binary("''"), None);
+    }
+
+    #[test]
+    fn files_belong_to_the_app_by_name_or_program() {
+        let gimp = desktop_app(
+            "gimp.desktop",
+            &[
+                ("Name", "GNU Image Manipulation Program"),
+                ("Exec", "gimp-2.10 %U"),
+            ],
+        );
+        let no_exec = || None;
+        assert!(recorded_by(
+            &gimp,
+            "GNU Image Manipulation Program",
+            no_exec
+        ));
+        assert!(recorded_by(&gimp, "GIMP", || Some(
+            "'gimp-2.10 %u'".to_string()
+        )));
+        assert!(recorded_by(&gimp, "GIMP", || Some(
+            "'/usr/bin/gimp-2.10' %u".to_string()
+        )));
+        assert!(!recorded_by(&gimp, "GIMP", || Some(
+            "'gimp %u'".to_string()
+        )));
+        assert!(!recorded_by(&gimp, "GIMP", no_exec));
+        assert!(!recorded_by(&gimp, "Krita", || Some(
+            "'krita %u'".to_string()
+        )));
+    }
+}
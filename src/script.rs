@@ -0,0 +1,384 @@
+use crate::custom::entry_icon;
+use crate::menu::{MenuItem, MenuItems};
+use crate::provider::{Item, ItemsFuture, Provider};
+use crate::utils::{ConfFile, get_conf_map};
+use dirs::home_dir;
+use eyre::{Result, eyre};
+use gtk4::{self as gtk, ApplicationWindow, gio, glib, prelude::*};
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+const MAX_RESULTS: usize = 200;
+// Gives up on a provider that hangs, it keeps a thread of the pool busy meanwhile.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+// How often a running query checks whether the search moved on.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Activates a result, with the id of one of its actions or `None` for the default.
+type ActivateItem = Rc<dyn Fn(Option<&str>)>;
+
+/// The executable registered for a prefix in "script-providers".
+#[derive(Clone)]
+struct ScriptCommand {
+    prefix: String,
+    command: String,
+}
+
+/// Results of an executable answering the queries typed after its prefix.
+pub struct ScriptProvider {
+    script: ScriptCommand,
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+struct ScriptAction {
+    id: String,
+    title: String,
+}
+
+struct ScriptItem {
+    id: String,
+    title: String,
+    subtitle: Option<String>,
+    icon: Option<String>,
+    actions: Vec<ScriptAction>,
+}
+
+/// Finds the provider registered for the query's first word, returning it with the rest of the query.
+pub fn script_query<'a>(
+    text: &'a str,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) -> Option<(ScriptProvider, &'a str)> {
+    let (prefix, query) = text.split_once(' ')?;
+    let conf = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok())?;
+    let (prefix, command) = get_conf_map(conf.read(), "script-providers")?
+        .into_iter()
+        .find(|(p, _)| p == prefix)?;
+    let provider = ScriptProvider {
+        script: ScriptCommand { prefix, command },
+        window: window.clone(),
+        app: app.clone(),
+    };
+    Some((provider, query))
+}
+
+impl ScriptCommand {
+    /// Starts the provider and sends it a single JSON line on stdin.
+    fn spawn(&self, message: Value, read_output: bool) -> Result<std::process::Child> {
+        let argv = glib::shell_parse_argv(&self.command)?;
+        let argv: Vec<String> = argv
+            .iter()
+            .map(|arg| {
+                let arg = arg.to_string_lossy();
+                match (arg.strip_prefix("~/"), home_dir()) {
+                    (Some(rest), Some(home)) => home.join(rest).to_string_lossy().to_string(),
+                    _ => arg.to_string(),
+                }
+            })
+            .collect();
+        let (program, args) = argv
+            .split_first()
+            .ok_or(eyre!("The \"{}\" provider has no command!", self.prefix))?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(if read_output {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "{}", message)?;
+        }
+        Ok(child)
+    }
+
+    /// Sends `{"event": "query", "query": …}` and reads one JSON result per output line.
+    /// Blocks, the provider is killed when the search is cancelled or takes too long.
+    fn query(&self, query: &str, cancellable: &gio::Cancellable) -> Result<Vec<ScriptItem>> {
+        let mut child = self.spawn(json!({ "event": "query", "query": query }), true)?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or(eyre!("Couldn't read the \"{}\" provider!", self.prefix))?;
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let lines: Vec<String> = BufReader::new(stdout)
+                .lines()
+                .map_while(|line| line.ok())
+                .take(MAX_RESULTS)
+                .collect();
+            let _ = sender.send(lines);
+        });
+        let started = Instant::now();
+        loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(lines) => {
+                    let _ = child.wait();
+                    return Ok(lines.iter().filter_map(|line| parse_item(line)).collect());
+                }
+                Err(RecvTimeoutError::Timeout)
+                    if !cancellable.is_cancelled() && started.elapsed() < QUERY_TIMEOUT => {}
+                Err(_) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    if cancellable.is_cancelled() {
+                        return Ok(Vec::new());
+                    }
+                    return Err(eyre!(
+                        "The \"{}\" provider didn't answer in time.",
+                        self.prefix
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Sends `{"event": "activate", "id": …, "action": …}`, the provider does the rest.
+    fn activate(&self, query: &str, id: &str, action: Option<&str>) {
+        let message = json!({ "event": "activate", "query": query, "id": id, "action": action });
+        if let Err(err) = self.spawn(message, false) {
+            eprintln!("Failed to run the \"{}\" provider: {}", self.prefix, err);
+        }
+    }
+}
+
+fn parse_item(line: &str) -> Option<ScriptItem> {
+    let item: Value = serde_json::from_str(line.trim()).ok()?;
+    let text = |value: &Value, key: &str| value.get(key)?.as_str().map(|s| s.to_string());
+    let actions = item
+        .get("actions")
+        .and_then(|a| a.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|action| {
+            Some(ScriptAction {
+                id: text(action, "id")?,
+                title: text(action, "title")?,
+            })
+        })
+        .collect();
+
+    Some(ScriptItem {
+        id: text(&item, "id")?,
+        title: text(&item, "title")?,
+        subtitle: text(&item, "subtitle"),
+        icon: text(&item, "icon"),
+        actions,
+    })
+}
+
+fn script_items(
+    script: &ScriptCommand,
+    results: Vec<ScriptItem>,
+    query: &str,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) -> Vec<Item> {
+    let script = Rc::new(script.clone());
+    let mut items = Vec::new();
+    for result in results {
+        let activate: ActivateItem = {
+            let script = script.clone();
+            let query = query.to_string();
+            let id = result.id.clone();
+            let window = window.clone();
+            let app = app.clone();
+            Rc::new(move |action| {
+                window.close();
+                script.activate(&query, &id, action);
+                app.quit();
+            })
+        };
+        let icon = entry_icon(result.icon.as_deref().unwrap_or("system-run"));
+        let activate_clone = activate.clone();
+        let mut item = Item::new(&result.title, icon, Rc::new(move || activate_clone(None)));
+        item.subtitle = result.subtitle;
+
+        if !result.actions.is_empty() {
+            let actions = Rc::new(result.actions);
+            item.actions = Some(Rc::new(move || {
+                let mut items: MenuItems = Vec::new();
+                for action in actions.iter() {
+                    let activate = activate.clone();
+                    let id = action.id.clone();
+                    items.push(MenuItem::Action(
+                        action.title.clone(),
+                        std::boxed::Box::new(move || activate(Some(&id))),
+                    ));
+                }
+                items
+            }));
+        }
+        items.push(item);
+    }
+    items
+}
+
+impl Provider for ScriptProvider {
+    fn name(&self) -> &'static str {
+        "script"
+    }
+
+    fn title(&self) -> &'static str {
+        "Results"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let results = self
+            .script
+            .query(query, &gio::Cancellable::new())
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                Vec::new()
+            });
+        script_items(&self.script, results, query, &self.window, &self.app)
+    }
+
+    fn query_async(&self, query: &str, cancellable: &gio::Cancellable) -> ItemsFuture {
+        let script = self.script.clone();
+        let query = query.to_string();
+        let cancellable = cancellable.clone();
+        let window = self.window.clone();
+        let app = self.app.clone();
+        std::boxed::Box::pin(async move {
+            let results = {
+                let script = script.clone();
+                let query = query.clone();
+                gio::spawn_blocking(move || script.query(&query, &cancellable)).await
+            };
+            match results {
+                Ok(Ok(results)) => script_items(&script, results, &query, &window, &app),
+                Ok(Err(err)) => {
+                    eprintln!("{}", err);
+                    Vec::new()
+                }
+                Err(_) => {
+                    eprintln!("The \"{}\" provider panicked.", script.prefix);
+                    Vec::new()
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// A provider running `body` as a shell script in a directory of its own.
+    fn script(name: &str, body: &str) -> (ScriptCommand, PathBuf) {
+        let dir = std::env::temp_dir().join(format!(
+            "void-launcher-script-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("provider.sh");
+        fs::write(&file, format!("cd '{}'\n{}", dir.display(), body)).unwrap();
+        let command = ScriptCommand {
+            prefix: name.to_string(),
+            command: format!("sh '{}'", file.display()),
+        };
+        (command, dir)
+    }
+
+    fn received(dir: &Path) -> Value {
+        let file = dir.join("received");
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(5) {
+            if let Some(value) = fs::read_to_string(&file)
+                .ok()
+                .and_then(|line| serde_json::from_str(&line).ok())
+            {
+                return value;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        panic!("The provider never got a message");
+    }
+
+    #[test]
+    fn items_need_an_id_and_a_title() {
+        let item = parse_item(
+            r#" {"id": "1", "title": "One", "subtitle": "first", "icon": "folder",
+                 "actions": [{"id": "open", "title": "Open"}, {"id": "broken"}]} "#,
+        )
+        .unwrap();
+        assert_eq!(item.id, "1");
+        assert_eq!(item.title, "One");
+        assert_eq!(item.subtitle.as_deref(), Some("first"));
+        assert_eq!(item.icon.as_deref(), Some("folder"));
+        let actions: Vec<(&str, &str)> = item
+            .actions
+            .iter()
+            .map(|action| (action.id.as_str(), action.title.as_str()))
+            .collect();
+        assert_eq!(actions, [("open", "Open")]);
+
+        let bare = parse_item(r#"{"id": "2", "title": "Two", "subtitle": 2}"#).unwrap();
+        assert!(bare.subtitle.is_none() && bare.icon.is_none() && bare.actions.is_empty());
+
+        assert!(parse_item(r#"{"title": "No id"}"#).is_none());
+        assert!(parse_item(r#"{"id": "3"}"#).is_none());
+        assert!(parse_item(r#"{"id": 4, "title": "Number"}"#).is_none());
+        assert!(parse_item("not json").is_none());
+        assert!(parse_item("").is_none());
+    }
+
+    #[test]
+    fn queries_send_the_event_and_read_a_result_per_line() {
+        let (command, dir) = script(
+            "query",
+            "read -r line\n\
+             printf '%s\\n' \"$line\" > received\n\
+             echo '{\"id\": \"a\", \"title\": \"Alpha\"}'\n\
+             echo 'garbage'\n\
+             echo '{\"id\": \"b\", \"title\": \"Beta\"}'\n",
+        );
+        let results = command
+            .query("hello world", &gio::Cancellable::new())
+            .unwrap();
+        let ids: Vec<&str> = results.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(
+            received(&dir),
+            json!({ "event": "query", "query": "hello world" })
+        );
+    }
+
+    #[test]
+    fn activation_sends_the_item_and_action() {
+        let (command, dir) = script(
+            "activate",
+            "read -r line\nprintf '%s\\n' \"$line\" > received.tmp\nmv received.tmp received\n",
+        );
+        command.activate("hello", "a", Some("open"));
+        assert_eq!(
+            received(&dir),
+            json!({ "event": "activate", "query": "hello", "id": "a", "action": "open" })
+        );
+    }
+
+    #[test]
+    fn cancelled_queries_stop_the_provider() {
+        let (command, _) = script("cancel", "sleep 30\n");
+        let cancellable = gio::Cancellable::new();
+        cancellable.cancel();
+        let started = Instant::now();
+        assert!(command.query("slow", &cancellable).unwrap().is_empty());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::matcher::matches;
+use crate::provider::{Item, Provider};
+use crate::utils::terminal_command;
+use dirs::home_dir;
+use gtk4::{self as gtk, ApplicationWindow, gio::ThemedIcon, prelude::*};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+
+const MAX_RESULTS: usize = 100;
+// Same limit as ssh itself, stops Include loops.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+struct SshHost {
+    alias: String,
+    port: Option<String>,
+    // HostName of a config entry, shown in the tooltip.
+    hostname: Option<String>,
+}
+
+/// Splits the "ssh " prefix off the query.
+pub fn ssh_query(text: &str) -> Option<&str> {
+    text.strip_prefix("ssh ")
+}
+
+fn ssh_dir() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(".ssh"))
+}
+
+fn is_pattern(host: &str) -> bool {
+    host.contains(['*', '?', '!'])
+}
+
+/// Splits a config line into its keyword and arguments, "Key value" and "Key=value" are both valid.
+fn config_line(line: &str) -> Option<(String, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let split = line.find(|c: char| c.is_whitespace() || c == '=')?;
+    let (keyword, rest) = line.split_at(split);
+    let rest = rest.trim_start().trim_start_matches('=').trim();
+    Some((keyword.to_lowercase(), rest))
+}
+
+/// Include paths are relative to ~/.ssh and may contain globs in the file name.
+fn include_paths(pattern: &str) -> Vec<PathBuf> {
+    let path = match pattern.strip_prefix("~/") {
+        Some(rest) => home_dir().unwrap_or_default().join(rest),
+        None if pattern.starts_with('/') => PathBuf::from(pattern),
+        None => ssh_dir().unwrap_or_default().join(pattern),
+    };
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    if !name.contains(['*', '?']) {
+        return vec![path];
+    }
+
+    let glob = format!(
+        "^{}$",
+        regex::escape(&name)
+            .replace(r"\*", ".*")
+            .replace(r"\?", ".")
+    );
+    let (Some(dir), Ok(glob)) = (path.parent(), Regex::new(&glob)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| glob.is_match(&entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn parse_config(path: &Path, hosts: &mut Vec<SshHost>, depth: usize) {
+    if depth > MAX_INCLUDE_DEPTH {
+        return;
+    }
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+    // Indexes of the hosts declared by the current Host block.
+    let mut block: Vec<usize> = Vec::new();
+    for (keyword, args) in contents.lines().filter_map(config_line) {
+        match keyword.as_str() {
+            "include" => {
+                for pattern in args.split_whitespace() {
+                    for included in include_paths(pattern) {
+                        parse_config(&included, hosts, depth + 1);
+                    }
+                }
+            }
+            "host" => {
+                block.clear();
+                for alias in args.split_whitespace().filter(|alias| !is_pattern(alias)) {
+                    if hosts.iter().any(|host| host.alias == alias) {
+                        continue;
+                    }
+                    block.push(hosts.len());
+                    hosts.push(SshHost {
+                        alias: alias.to_string(),
+                        port: None,
+                        hostname: None,
+                    });
+                }
+            }
+            "match" => block.clear(),
+            "hostname" => {
+                for &index in &block {
+                    hosts[index].hostname = Some(args.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Plain known_hosts entries, hashed ones can't be turned back into names.
+fn parse_known_hosts(path: &Path, hosts: &mut Vec<SshHost>) {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', '|', '@']) {
+            continue;
+        }
+        let Some(names) = line.split_whitespace().next() else {
+            continue;
+        };
+        for name in names.split(',').filter(|name| !is_pattern(name)) {
+            // Non-default ports are stored as "[host]:port".
+            let (alias, port) = match name
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once("]:"))
+            {
+                Some((host, port)) => (host, Some(port.to_string())),
+                None => (name, None),
+            };
+            if hosts
+                .iter()
+                .any(|host| host.alias == alias || host.hostname.as_deref() == Some(alias))
+            {
+                continue;
+            }
+            hosts.push(SshHost {
+                alias: alias.to_string(),
+                port,
+                hostname: None,
+            });
+        }
+    }
+}
+
+/// Hosts from ~/.ssh/config first, then the ones only found in known_hosts.
+fn load_hosts() -> Vec<SshHost> {
+    let mut hosts = Vec::new();
+    if let Some(dir) = ssh_dir() {
+        parse_config(&dir.join("config"), &mut hosts, 0);
+        parse_known_hosts(&dir.join("known_hosts"), &mut hosts);
+    }
+    hosts
+}
+
+fn ssh_args(host: &SshHost) -> Vec<&str> {
+    let mut args = vec!["ssh"];
+    if let Some(port) = &host.port {
+        args.extend(["-p", port]);
+    }
+    args.push(&host.alias);
+    args
+}
+
+fn open_ssh(host: &SshHost) {
+    let Some(command) = terminal_command(&ssh_args(host)) else {
+        eprintln!("Couldn't find a terminal, set \"terminal\" in your config file.");
+        return;
+    };
+    Command::new(&command[0])
+        .args(&command[1..])
+        .spawn()
+        .map(|_| ())
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to start {}: {}", command[0], err);
+        });
+}
+
+/// Hosts of `~/.ssh/config` and `known_hosts`, each opened in a terminal.
+pub struct SshProvider {
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl SshProvider {
+    pub fn new(window: &ApplicationWindow, app: &gtk::Application) -> Self {
+        Self {
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+}
+
+impl Provider for SshProvider {
+    fn name(&self) -> &'static str {
+        "ssh"
+    }
+
+    fn title(&self) -> &'static str {
+        "SSH"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let query = query.trim();
+        load_hosts()
+            .into_iter()
+            .filter(|host| {
+                let keywords: Vec<String> = host.hostname.iter().cloned().collect();
+                matches(query, &host.alias, &keywords)
+            })
+            .take(MAX_RESULTS)
+            .map(|host| {
+                let tooltip = match &host.hostname {
+                    Some(hostname) => format!("{} ({})", ssh_args(&host).join(" "), hostname),
+                    None => ssh_args(&host).join(" "),
+                };
+                let title = host.alias.clone();
+                let window = self.window.clone();
+                let app = self.app.clone();
+                let mut item = Item::new(
+                    &title,
+                    ThemedIcon::new("network-server").upcast(),
+                    Rc::new(move || {
+                        window.close();
+                        open_ssh(&host);
+                        app.quit();
+                    }),
+                );
+                item.subtitle = Some(tooltip);
+                item.highlight(query);
+                item
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `files` into a directory of their own and returns it.
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("void-launcher-ssh-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    fn aliases(hosts: &[SshHost]) -> Vec<&str> {
+        hosts.iter().map(|host| host.alias.as_str()).collect()
+    }
+
+    #[test]
+    fn config_lines_take_both_separators() {
+        assert_eq!(
+            config_line("  HostName example.org"),
+            Some(("hostname".to_string(), "example.org"))
+        );
+        assert_eq!(config_line("Port=2222"), Some(("port".to_string(), "2222")));
+        assert_eq!(
+            config_line("Port = 2222"),
+            Some(("port".to_string(), "2222"))
+        );
+        assert_eq!(config_line("# Host commented"), None);
+        assert_eq!(config_line("   "), None);
+    }
+
+    #[test]
+    fn hosts_skip_patterns_and_take_their_hostname() {
+        let dir = write_files(
+            "hosts",
+            &[(
+                "config",
+                "Host *\n  ServerAliveInterval 30\n\
+                 Host web web-backup *.internal !bastion\n  HostName web.example.org\n\
+                 Match host db\n  HostName ignored.example.org\n\
+                 Host db\n  HostName=db.example.org\n\
+                 Host web\n  HostName other.example.org\n",
+            )],
+        );
+        let mut hosts = Vec::new();
+        parse_config(&dir.join("config"), &mut hosts, 0);
+
+        assert_eq!(aliases(&hosts), ["web", "web-backup", "db"]);
+        assert_eq!(hosts[0].hostname.as_deref(), Some("web.example.org"));
+        assert_eq!(hosts[1].hostname.as_deref(), Some("web.example.org"));
+        assert_eq!(hosts[2].hostname.as_deref(), Some("db.example.org"));
+    }
+
+    #[test]
+    fn includes_follow_globs_and_stop_at_loops() {
+        let dir = write_files("include", &[]);
+        let config = format!(
+            "Host first\nInclude {0}/conf.d/*.conf\nInclude {0}/config\nHost last\n",
+            dir.display()
+        );
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+        fs::write(dir.join("config"), config).unwrap();
+        fs::write(dir.join("conf.d/b.conf"), "Host beta\n").unwrap();
+        fs::write(dir.join("conf.d/a.conf"), "Host alpha\n").unwrap();
+        fs::write(dir.join("conf.d/notes.txt"), "Host skipped\n").unwrap();
+
+        let mut hosts = Vec::new();
+        parse_config(&dir.join("config"), &mut hosts, 0);
+        assert_eq!(aliases(&hosts), ["first", "alpha", "beta", "last"]);
+    }
+
+    #[test]
+    fn known_hosts_keep_their_port() {
+        let dir = write_files(
+            "known",
+            &[(
+                "known_hosts",
+                "# comment\n\
+                 db.example.org,10.0.0.2 ssh-ed25519 AAAA\n\
+                 [git.example.org]:2222 ssh-ed25519 AAAA\n\
+                 |1|c2FsdA==|aGFzaA== ssh-ed25519 AAAA\n\
+                 @revoked old.example.org ssh-rsa AAAA\n\
+                 *.example.net ssh-rsa AAAA\n",
+            )],
+        );
+        let mut hosts = vec![SshHost {
+            alias: "db".to_string(),
+            port: None,
+            hostname: Some("db.example.org".to_string()),
+        }];
+        parse_known_hosts(&dir.join("known_hosts"), &mut hosts);
+
+        assert_eq!(aliases(&hosts), ["db", "10.0.0.2", "git.example.org"]);
+        assert_eq!(hosts[2].port.as_deref(), Some("2222"));
+        assert_eq!(
+            ssh_args(&hosts[2]),
+            ["ssh", "-p", "2222", "git.example.org"]
+        );
+        assert_eq!(ssh_args(&hosts[1]), ["ssh", "10.0.0.2"]);
+    }
+}
@@ -1,23 +1,34 @@
 use std::cell::RefCell;
 use std::path::PathBuf;
+use std::time::Instant;
 
+use crate::apps::{InstalledApps, refresh_index};
+use crate::categories::{build_category_bar, refresh_category_bar};
+use crate::compositor::Compositor;
 use crate::constants::UI_XML;
-use crate::events::{apps_events, events, start_animations};
+use crate::events::{events, start_animations};
 use crate::is_dark_theme_active;
-use crate::utils::{ConfFile, get_conf_data, string_to_i32, string_to_u32};
+use crate::provider::{Providers, query_all, render_sections};
+use crate::utils::{ConfFile, get_conf_data, get_conf_option, string_to_i32, string_to_u32};
 use dirs::home_dir;
 use eyre::{Ok, Result, eyre};
 use gtk4::{
-    self as gtk, ApplicationWindow, Box, Builder, Button, CssProvider, FlowBox, Image, Label,
-    Orientation,
-    gio::AppInfo,
+    self as gtk, ApplicationWindow, Builder, CssProvider, FlowBox, Image,
+    gdk::{Display, Monitor},
+    gio::AppInfoMonitor,
     glib::{self, object::IsA},
     prelude::*,
 };
 use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
 use std::rc::Rc;
 
-pub fn build_ui(app: &gtk::Application, css: String, provider: CssProvider) -> Result<()> {
+pub fn build_ui(
+    app: &gtk::Application,
+    css: String,
+    provider: CssProvider,
+    mode: String,
+    benchmark: Option<Instant>,
+) -> Result<()> {
     let builder = Builder::from_string(UI_XML);
 
     let window: ApplicationWindow = get_object(&builder, "window")?;
@@ -35,8 +46,14 @@ pub fn build_ui(app: &gtk::Application, css: String, provider: CssProvider) -> R
         let columns_mode_string = get_conf_data(conf.read(), "columns");
         let mut icon_size_string = get_conf_data(conf.read(), "icon_size");
         let icon_size_memory = Rc::new(RefCell::new(0));
+        let compositor_mode = get_conf_option(conf.read(), "compositor");
 
         window.init_layer_shell();
+        if let Some(monitor) =
+            Compositor::detect(&compositor_mode).and_then(|c| focused_monitor(&c))
+        {
+            window.set_monitor(Some(&monitor));
+        }
         if fullscreen == "false" {
             window.set_anchor(Edge::Bottom, true);
             if width < 350 {
@@ -104,60 +121,80 @@ pub fn build_ui(app: &gtk::Application, css: String, provider: CssProvider) -> R
         }
 
         flowbox.set_row_spacing(15);
-        let app_infos = AppInfo::all();
-        let mut app_images = Vec::new();
-        for appynka in app_infos {
-            // Filter only apps with show-in UI
-            if appynka.should_show() {
-                let name = appynka.name();
-                let err = format!("Failed to process the icon of app: {}.", name);
-                let icon = appynka.icon().expect(&err);
-
-                let label = Label::new(Some(&name));
-                label.set_justify(gtk::Justification::Fill);
-                label.add_css_class("app-label");
-                label.set_ellipsize(pango::EllipsizeMode::End);
-                label.set_max_width_chars(5); // or whatever works for your icon size
-                label.set_wrap(false);
-                let image = Image::from_gicon(&icon);
-
-                let icon_size;
-                if icon_size_string != "auto" {
-                    let icon_size_int = string_to_i32(icon_size_string.clone(), "");
-                    if (icon_size_int >= 20) && (icon_size_int <= 80) {
-                        icon_size = icon_size_int;
-                        *icon_size_memory.borrow_mut() = icon_size_int;
+        let icon_size;
+        if icon_size_string != "auto" {
+            let icon_size_int = string_to_i32(icon_size_string.clone(), "");
+            if (icon_size_int >= 20) && (icon_size_int <= 80) {
+                icon_size = icon_size_int;
+                *icon_size_memory.borrow_mut() = icon_size_int;
+            } else {
+                println!(
+                    "\"{}\" isn't a valid value for \"icon_size\", going with \"auto\".",
+                    icon_size_string
+                );
+                icon_size = 30;
+                icon_size_string = "auto".to_string();
+            }
+        } else {
+            icon_size = 30;
+        }
+
+        let installed = InstalledApps::default();
+        let providers = Rc::new(Providers::new(
+            &builder,
+            app,
+            icon_size_memory.clone(),
+            &installed,
+        )?);
+        // Apps are drawn right away so their icons can be resized with the window,
+        // the other modes may search in the background and start out like typing does.
+        let (app_images, from_index) = if mode == "apps" {
+            let route = providers.route("", &mode);
+            let indexed = providers.apps().startup_items();
+            let from_index = indexed.is_some();
+            let sections = query_all(&route, indexed);
+            let images = render_sections(&flowbox, sections, &route.layout, icon_size);
+            (images, from_index)
+        } else {
+            (Vec::new(), false)
+        };
+        let search_entry: gtk::Entry = get_object(&builder, "search-entry")?;
+        let apps_mode = mode == "apps";
+
+        // Whatever reads the desktop files waits for the first frame.
+        if let Some(started) = benchmark {
+            let app = app.clone();
+            after_first_frame(&window, move || {
+                println!(
+                    "First frame after {} ms, app index {}.",
+                    started.elapsed().as_millis(),
+                    if from_index {
+                        "from the cache"
                     } else {
-                        println!(
-                            "\"{}\" isn't a valid value for \"icon_size\", going with \"auto\".",
-                            icon_size_string
-                        );
-                        icon_size = 30;
-                        icon_size_string = "auto".to_string();
+                        "read from the desktop files"
                     }
-                } else {
-                    icon_size = 30;
+                );
+                app.quit();
+            });
+        } else if apps_mode {
+            let builder = builder.clone();
+            let installed = installed.clone();
+            after_first_frame(&window, move || {
+                if let Err(err) = build_category_bar(&builder, &installed) {
+                    eprintln!("Failed to build the category bar: {}", err);
                 }
-
-                image.set_pixel_size(icon_size);
-                image.set_valign(gtk4::Align::Center);
-                image.set_halign(gtk4::Align::Center);
-                app_images.push(image.clone());
-                let app_box = Box::new(Orientation::Vertical, 5);
-                app_box.append(&image);
-                app_box.append(&label);
-                app_box.add_css_class("appynka");
-                let app_button = Button::builder().child(&app_box).build();
-                app_button.add_css_class("flat");
-                app_button.add_css_class("app-button");
-                unsafe {
-                    app_button.set_data("app-info", appynka.clone());
+                if !from_index {
+                    return;
                 }
-
-                flowbox.insert(&app_button, -1);
-
-                apps_events(appynka, app_button, builder.clone(), app.clone())?;
-            }
+                // The cached grid stays unless the desktop files tell otherwise.
+                let entry: Option<gtk::Entry> = builder.object("search-entry");
+                glib::spawn_future_local(async move {
+                    let changed = refresh_index().await;
+                    if let Some(entry) = entry.filter(|e| changed && e.text().is_empty()) {
+                        entry.emit_by_name::<()>("changed", &[]);
+                    }
+                });
+            });
         }
 
         let window_clone = window.clone();
@@ -206,7 +243,38 @@ pub fn build_ui(app: &gtk::Application, css: String, provider: CssProvider) -> R
                 .expect("Failed to execute function start_animations");
         });
 
-        events(app.to_owned(), builder, icon_size_memory.clone())?;
+        // Installing or removing apps refreshes the shared app list and the category
+        // bar, then runs the search again. gio drops the monitor with its last
+        // reference, so the window holds on to it.
+        let monitor = AppInfoMonitor::get();
+        {
+            let builder = builder.clone();
+            let search_entry = search_entry.clone();
+            monitor.connect_changed(move |_| {
+                installed.refresh();
+                refresh_category_bar(&builder, &installed);
+                if apps_mode {
+                    let search_entry = search_entry.clone();
+                    glib::idle_add_local_once(move || {
+                        search_entry.emit_by_name::<()>("changed", &[]);
+                    });
+                }
+            });
+        }
+        unsafe {
+            window.set_data("app-info-monitor", monitor);
+        }
+
+        events(
+            app.to_owned(),
+            builder,
+            icon_size_memory.clone(),
+            mode,
+            providers,
+        )?;
+        if !apps_mode {
+            search_entry.emit_by_name::<()>("changed", &[]);
+        }
 
         app.connect_activate(move |_| {
             window.present();
@@ -220,6 +288,45 @@ pub fn build_ui(app: &gtk::Application, css: String, provider: CssProvider) -> R
     Ok(())
 }
 
+/// Runs `f` once the window has painted its first frame.
+fn after_first_frame<F>(window: &ApplicationWindow, f: F)
+where
+    F: FnOnce() + 'static,
+{
+    let f = Rc::new(RefCell::new(Some(f)));
+    window.connect_map(move |window| {
+        let Some(clock) = window.frame_clock() else {
+            return;
+        };
+        let handler = Rc::new(RefCell::new(None));
+        let handler_clone = handler.clone();
+        let f = f.clone();
+        let id = clock.connect_after_paint(move |clock| {
+            if let Some(f) = f.borrow_mut().take() {
+                glib::idle_add_local_once(f);
+            }
+            if let Some(id) = handler_clone.borrow_mut().take() {
+                clock.disconnect(id);
+            }
+        });
+        *handler.borrow_mut() = Some(id);
+    });
+}
+
+fn focused_monitor(compositor: &Compositor) -> Option<Monitor> {
+    let output = match compositor.focused_output() {
+        std::result::Result::Ok(output) => output?,
+        Err(err) => {
+            eprintln!("Failed to query the focused output: {}", err);
+            return None;
+        }
+    };
+    let monitors = Display::default()?.monitors();
+    (0..monitors.n_items())
+        .filter_map(|i| monitors.item(i).and_downcast::<Monitor>())
+        .find(|monitor| monitor.connector().as_deref() == Some(output.as_str()))
+}
+
 pub fn get_object<T>(builder: &Builder, name: &str) -> Result<T>
 where
     T: IsA<gtk4::glib::Object>,
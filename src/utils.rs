@@ -1,4 +1,7 @@
-use serde_json::Value;
+use crate::constants::DEFAULT_JSON;
+use dirs::home_dir;
+use gtk4::glib;
+use serde_json::{Map, Value};
 use std::fs;
 use std::path::PathBuf;
 pub struct ConfFile {
@@ -16,6 +19,18 @@ impl ConfFile {
     }
 }
 
+/// The option from the config, or from the default config when the file was
+/// written before the option existed.
+fn conf_value(conf: &str, which: &str) -> Option<Value> {
+    let find = |json: &str| {
+        let data: Value = serde_json::from_str(json).expect("Failed to get data from json");
+        data.as_array()
+            .and_then(|data_array| data_array.iter().find_map(|entry| entry.get(which)))
+            .cloned()
+    };
+    find(conf).or_else(|| find(DEFAULT_JSON))
+}
+
 pub fn get_conf_data(conf: String, which: &str) -> String {
     let mut out = String::new();
     let data: Value = serde_json::from_str(&conf).expect("Failed to get data from json");
@@ -33,6 +48,47 @@ pub fn get_conf_data(conf: String, which: &str) -> String {
     out
 }
 
+/// Like `get_conf_data`, but for options newer than the config file may be. A config
+/// written before the option existed gets its default without a warning.
+pub fn get_conf_option(conf: String, which: &str) -> String {
+    conf_value(&conf, which)
+        .and_then(|target| target.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+/// Like `get_conf_data`, but for options holding an array of strings.
+pub fn get_conf_list(conf: String, which: &str) -> Option<Vec<String>> {
+    conf_value(&conf, which)
+        .as_ref()
+        .and_then(|target| target.as_array())
+        .map(|list| {
+            list.iter()
+                .filter_map(|s| s.as_str())
+                .map(|s| s.to_string())
+                .collect()
+        })
+}
+
+/// Like `get_conf_data`, but for options holding an object of strings.
+pub fn get_conf_map(conf: String, which: &str) -> Option<Vec<(String, String)>> {
+    conf_value(&conf, which)
+        .as_ref()
+        .and_then(|target| target.as_object())
+        .map(|map| {
+            map.iter()
+                .filter_map(|(key, value)| Some((key.to_string(), value.as_str()?.to_string())))
+                .collect()
+        })
+}
+
+/// Like `get_conf_data`, but for options holding an array of objects.
+pub fn get_conf_objects(conf: String, which: &str) -> Option<Vec<Map<String, Value>>> {
+    conf_value(&conf, which)
+        .as_ref()
+        .and_then(|target| target.as_array())
+        .map(|list| list.iter().filter_map(|o| o.as_object()).cloned().collect())
+}
+
 pub fn string_to_i32(input: String, which: &str) -> i32 {
     let out;
     match input.parse::<i32>() {
@@ -68,3 +124,76 @@ pub fn string_to_u32(input: String) -> u32 {
     }
     out
 }
+
+/// Drops the desktop entry field codes (%U, %f, ...) from an Exec line.
+pub fn strip_field_codes(exec: &str) -> String {
+    let mut out = String::new();
+    let mut chars = exec.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            if chars.peek() == Some(&'%') {
+                out.push('%');
+            }
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+    out.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// Terminals tried by "terminal": "auto", with the arguments that make them run a command.
+const TERMINALS: [&[&str]; 8] = [
+    &["foot"],
+    &["kitty"],
+    &["alacritty", "-e"],
+    &["wezterm", "start", "--"],
+    &["ghostty", "-e"],
+    &["konsole", "-e"],
+    &["gnome-terminal", "--"],
+    &["xterm", "-e"],
+];
+
+/// The command line running `command` in the "terminal" from the config, which is
+/// a command the arguments get appended to, like "foot" or "alacritty -e".
+pub fn terminal_command(command: &[&str]) -> Option<Vec<String>> {
+    let conf = home_dir()
+        .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+    let terminal = conf
+        .map(|conf| get_conf_option(conf.read(), "terminal"))
+        .unwrap_or_default();
+
+    let mut line: Vec<String> = if !terminal.is_empty() && terminal != "auto" {
+        terminal.split_whitespace().map(|s| s.to_string()).collect()
+    } else if let Some(env_terminal) = std::env::var("TERMINAL")
+        .ok()
+        .filter(|t| glib::find_program_in_path(t).is_some())
+    {
+        vec![env_terminal, "-e".to_string()]
+    } else {
+        TERMINALS
+            .iter()
+            .find(|args| glib::find_program_in_path(args[0]).is_some())?
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    };
+    line.extend(command.iter().map(|s| s.to_string()));
+    Some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_field_codes_keeps_escaped_percents() {
+        assert_eq!(strip_field_codes("firefox %u"), "firefox");
+        assert_eq!(
+            strip_field_codes("env FOO=1 code --new-window %F"),
+            "env FOO=1 code --new-window"
+        );
+        assert_eq!(strip_field_codes("printf 100%% %i %c"), "printf 100%");
+        assert_eq!(strip_field_codes("gimp-2.10 %U "), "gimp-2.10");
+    }
+}
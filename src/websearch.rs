@@ -0,0 +1,252 @@
+use crate::provider::{Item, Provider};
+use crate::utils::{ConfFile, get_conf_map, get_conf_option};
+use dirs::home_dir;
+use gtk4::{
+    self as gtk, ApplicationWindow,
+    gio::{AppInfo, AppLaunchContext, Icon, ThemedIcon},
+    glib,
+    prelude::*,
+};
+use std::rc::Rc;
+
+const DEFAULT_ENGINES: [(&str, &str); 5] = [
+    ("g", "https://www.google.com/search?q={q}"),
+    ("ddg", "https://duckduckgo.com/?q={q}"),
+    ("wiki", "https://en.wikipedia.org/w/index.php?search={q}"),
+    ("crates", "https://crates.io/search?q={q}"),
+    ("docs.rs", "https://docs.rs/releases/search?query={q}"),
+];
+
+pub struct WebSearch {
+    pub keyword: String,
+    pub query: String,
+    pub url: String,
+}
+
+fn search_url(template: &str, query: &str) -> String {
+    template.replace("{q}", &glib::Uri::escape_string(query, None, true))
+}
+
+/// "search-engines" and the "search-default" engine, read once.
+pub struct SearchEngines {
+    engines: Vec<(String, String)>,
+    // None with "search-default": "none".
+    default: Option<(String, String)>,
+}
+
+impl SearchEngines {
+    pub fn load() -> Self {
+        let conf = home_dir()
+            .and_then(|home| ConfFile::new(home.join(".config/void-launcher/config.json")).ok());
+        let engines: Vec<(String, String)> = conf
+            .as_ref()
+            .and_then(|conf| get_conf_map(conf.read(), "search-engines"))
+            .unwrap_or_else(|| {
+                DEFAULT_ENGINES
+                    .iter()
+                    .map(|(keyword, url)| (keyword.to_string(), url.to_string()))
+                    .collect()
+            });
+        let default = conf
+            .map(|conf| get_conf_option(conf.read(), "search-default"))
+            .unwrap_or_default();
+        let default = (default != "none").then(|| {
+            engines
+                .iter()
+                .find(|(k, _)| *k == default)
+                .cloned()
+                .unwrap_or_else(|| {
+                    println!(
+                        "\"{}\" isn't one of your \"search-engines\", going with default: \"ddg\".",
+                        default
+                    );
+                    (
+                        DEFAULT_ENGINES[1].0.to_string(),
+                        DEFAULT_ENGINES[1].1.to_string(),
+                    )
+                })
+        });
+
+        Self { engines, default }
+    }
+
+    /// "ddg rust closures" searches DuckDuckGo when "ddg" is one of the "search-engines".
+    fn bang_search(&self, text: &str) -> Option<WebSearch> {
+        let (keyword, query) = text.trim_start().split_once(' ')?;
+        let query = query.trim();
+        if query.is_empty() {
+            return None;
+        }
+        let (keyword, template) = self.engines.iter().find(|(k, _)| k == keyword)?;
+        Some(WebSearch {
+            url: search_url(template, query),
+            keyword: keyword.clone(),
+            query: query.to_string(),
+        })
+    }
+
+    /// The "Search the web for …" fallback through the "search-default" engine.
+    pub fn fallback_search(&self, text: &str) -> Option<WebSearch> {
+        let query = text.trim();
+        if query.is_empty() {
+            return None;
+        }
+        let (keyword, template) = self.default.as_ref()?;
+        Some(WebSearch {
+            url: search_url(template, query),
+            keyword: keyword.clone(),
+            query: query.to_string(),
+        })
+    }
+}
+
+// Typing a search keyword means searching, put it above the other blended results.
+const KEYWORD_SCORE: i64 = 1000;
+
+fn search_item(
+    search: WebSearch,
+    text: &str,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) -> Item {
+    let icon: Icon = AppInfo::default_for_uri_scheme("https")
+        .and_then(|browser| browser.icon())
+        .unwrap_or_else(|| ThemedIcon::new("web-browser").upcast());
+    let url = search.url.clone();
+    let window = window.clone();
+    let app = app.clone();
+    let mut item = Item::new(
+        text,
+        icon,
+        Rc::new(move || {
+            window.close();
+            let context = AppLaunchContext::new();
+            AppInfo::launch_default_for_uri(&url, Some(&context)).unwrap_or_else(|err| {
+                eprintln!("Failed to open {}: {}", url, err);
+            });
+            app.quit();
+        }),
+    );
+    item.subtitle = Some(search.url);
+    item
+}
+
+/// "Search the web for …", shown when nothing else matched.
+pub fn fallback_item(
+    search: WebSearch,
+    window: &ApplicationWindow,
+    app: &gtk::Application,
+) -> Item {
+    let text = format!("Search the web for {}", search.query);
+    search_item(search, &text, window, app)
+}
+
+/// Queries starting with one of the "search-engines" keywords.
+pub struct WebSearchProvider {
+    engines: Rc<SearchEngines>,
+    window: ApplicationWindow,
+    app: gtk::Application,
+}
+
+impl WebSearchProvider {
+    pub fn new(
+        engines: Rc<SearchEngines>,
+        window: &ApplicationWindow,
+        app: &gtk::Application,
+    ) -> Self {
+        Self {
+            engines,
+            window: window.clone(),
+            app: app.clone(),
+        }
+    }
+}
+
+impl Provider for WebSearchProvider {
+    fn name(&self) -> &'static str {
+        "web-search"
+    }
+
+    fn title(&self) -> &'static str {
+        "Web search"
+    }
+
+    fn query(&self, query: &str) -> Vec<Item> {
+        let Some(search) = self.engines.bang_search(query) else {
+            return Vec::new();
+        };
+        let text = format!("Search {} for {}", search.keyword, search.query);
+        let mut item = search_item(search, &text, &self.window, &self.app);
+        item.score = KEYWORD_SCORE;
+        vec![item]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engines(default: Option<usize>) -> SearchEngines {
+        let engines: Vec<(String, String)> = DEFAULT_ENGINES
+            .iter()
+            .map(|(keyword, url)| (keyword.to_string(), url.to_string()))
+            .collect();
+        SearchEngines {
+            default: default.map(|index| engines[index].clone()),
+            engines,
+        }
+    }
+
+    #[test]
+    fn queries_are_percent_encoded_into_the_template() {
+        assert_eq!(
+            search_url("https://duckduckgo.com/?q={q}", "rust & gtk 100%"),
+            "https://duckduckgo.com/?q=rust%20%26%20gtk%20100%25"
+        );
+        assert_eq!(
+            search_url("https://example.org/{q}/{q}", "a/b"),
+            "https://example.org/a%2Fb/a%2Fb"
+        );
+        // Browsers take UTF-8 as it is.
+        assert_eq!(
+            search_url("https://example.org/?q={q}", "čaj"),
+            "https://example.org/?q=čaj"
+        );
+    }
+
+    #[test]
+    fn keywords_pick_the_engine() {
+        let engines = engines(Some(1));
+        let search = engines.bang_search("  wiki  rust  closures ").unwrap();
+        assert_eq!(search.keyword, "wiki");
+        assert_eq!(search.query, "rust  closures");
+        assert_eq!(
+            search.url,
+            "https://en.wikipedia.org/w/index.php?search=rust%20%20closures"
+        );
+
+        assert_eq!(
+            engines.bang_search("docs.rs serde").unwrap().keyword,
+            "docs.rs"
+        );
+        // A keyword alone isn't a search yet, and only whole keywords count.
+        assert!(engines.bang_search("ddg ").is_none());
+        assert!(engines.bang_search("ddg").is_none());
+        assert!(engines.bang_search("dd rust").is_none());
+        assert!(engines.bang_search("DDG rust").is_none());
+    }
+
+    #[test]
+    fn fallback_goes_through_the_default_engine() {
+        let search = engines(Some(1))
+            .fallback_search("  firefox profiles ")
+            .unwrap();
+        assert_eq!(search.keyword, "ddg");
+        assert_eq!(search.query, "firefox profiles");
+        assert_eq!(search.url, "https://duckduckgo.com/?q=firefox%20profiles");
+
+        assert!(engines(Some(1)).fallback_search("   ").is_none());
+        // "search-default": "none"
+        assert!(engines(None).fallback_search("firefox").is_none());
+    }
+}